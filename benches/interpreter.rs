@@ -0,0 +1,89 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use chip8oxyde::chip8::engine::Engine;
+use chip8oxyde::chip8::Chip8;
+
+const STEPS: u64 = 10_000;
+
+// Arithmetic loop drawing a sprite every 256 iterations
+const PROGRAM: [u8; 19] = [
+    0x60, 0x00, // V0 = 0
+    0xA2, 0x12, // I = sprite
+    0x70, 0x01, // loop: V0 += 1
+    0x81, 0x04, // V1 += V0
+    0x82, 0x13, // V2 ^= V1
+    0x30, 0x00, // skip if V0 == 0
+    0x12, 0x04, // goto loop
+    0xD1, 0x21, // draw at (V1, V2)
+    0x12, 0x04, // goto loop
+    0x80, // sprite
+];
+
+// Tall sprites at random positions, with a clear on average every 8 of them
+const DRAW_PROGRAM: [u8; 31] = [
+    0xA2, 0x10, // I = sprite
+    0xC0, 0x3F, // loop: V0 = random x
+    0xC1, 0x1F, // V1 = random y
+    0xD0, 0x1F, // draw at (V0, V1)
+    0xC2, 0x07, // V2 = random 0 to 7
+    0x42, 0x00, // skip if V2 != 0
+    0x00, 0xE0, // clear
+    0x12, 0x02, // goto loop
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+fn chip8(engine: Engine, instruction_cache: bool) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load(&PROGRAM).unwrap();
+    chip8.set_engine(engine);
+    chip8.set_instruction_cache(instruction_cache);
+    chip8
+}
+
+// Instructions executed back to back, as in headless runs without the frame limit
+fn unthrottled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("unthrottled");
+    group.throughput(Throughput::Elements(STEPS));
+
+    let configurations = [
+        ("decode", Engine::Interpreter, false),
+        ("instruction_cache", Engine::Interpreter, true),
+        ("blocks", Engine::Blocks, true),
+    ];
+    for (name, engine, instruction_cache) in configurations.iter() {
+        let mut chip8 = chip8(*engine, *instruction_cache);
+        group.bench_function(*name, |b| {
+            b.iter(|| chip8.run_instructions(STEPS as usize).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+// Whole frames: the frame's instructions, timers, persistence and the colors a frontend reads
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+
+    let configurations = [
+        ("interpreter", Engine::Interpreter, false),
+        ("blocks", Engine::Blocks, false),
+        ("persistence", Engine::Interpreter, true),
+    ];
+    for (name, engine, persistence) in configurations.iter() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&DRAW_PROGRAM).unwrap();
+        chip8.set_engine(*engine);
+        chip8.set_persistence(*persistence);
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                chip8.run_frame().unwrap();
+                black_box(chip8.screen().pixel_colors());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, unthrottled, frame);
+criterion_main!(benches);
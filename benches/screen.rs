@@ -0,0 +1,67 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use chip8oxyde::chip8::screen::Screen;
+use chip8oxyde::config;
+
+// The tallest sprite, a checkerboard
+const SPRITE: [u8; 15] = [
+    0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA,
+];
+
+// Sprites are drawn on a grid covering the display and past its edges
+fn draw_all(screen: &mut Screen, clipping: bool) -> bool {
+    let mut collision = false;
+    for y in (0..config::CHIP8_HEIGHT as usize).step_by(8) {
+        for x in (0..config::CHIP8_WIDTH as usize).step_by(8) {
+            collision |= screen.draw_sprite(x + 4, y + 4, &SPRITE, clipping);
+        }
+    }
+
+    collision
+}
+
+fn draw_sprite(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_sprite");
+
+    for (name, clipping, persistence) in [
+        ("wrap", false, false),
+        ("clip", true, false),
+        ("persistence", false, true),
+    ]
+    .iter()
+    {
+        let mut screen = Screen::new();
+        screen.set_persistence(*persistence);
+        group.bench_function(*name, |b| b.iter(|| draw_all(&mut screen, *clipping)));
+    }
+
+    group.finish();
+}
+
+fn clear(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clear");
+
+    // Only the clear is timed, the display is drawn beforehand
+    for (name, persistence) in [("clear", false), ("persistence", true)].iter() {
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || {
+                    let mut screen = Screen::new();
+                    screen.set_persistence(*persistence);
+                    draw_all(&mut screen, false);
+                    screen
+                },
+                |mut screen| {
+                    screen.clear();
+                    screen
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, draw_sprite, clear);
+criterion_main!(benches);
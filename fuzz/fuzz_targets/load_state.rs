@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chip8oxyde::chip8::Chip8;
+
+// Loading arbitrary bytes as a save state must either fail or give a machine that runs
+// without panicking, and that saves back to the same bytes
+fuzz_target!(|data: &[u8]| {
+    let mut chip8 = Chip8::new();
+    if chip8.load_state(data).is_err() {
+        return;
+    }
+
+    assert_eq!(chip8.save_state(), data);
+
+    for _ in 0..60 {
+        if chip8.run_frame().is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,60 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chip8oxyde::chip8::quirks::QuirkProfile;
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::config;
+
+// Runs for 10 seconds of emulated time, or until the program fails
+const FRAMES: usize = 600;
+
+// The first byte picks the quirk profile and seeds the key presses, the rest is the program.
+// The interpreter must never panic, and a save state taken at the end must restore the same
+// machine, which then runs identically
+fuzz_target!(|data: &[u8]| {
+    let (seed, program) = match data.split_first() {
+        Some((seed, program)) => (*seed, program),
+        None => return,
+    };
+
+    let mut chip8 = Chip8::new();
+    if chip8.load(program).is_err() {
+        return;
+    }
+    chip8.seed(seed as u64);
+    chip8.set_quirks(QuirkProfile::ALL[seed as usize % QuirkProfile::ALL.len()].quirks());
+
+    // xorshift32, must not start at 0
+    let mut keys = seed as u32 | 0x100;
+    let mut next_key_event = || {
+        keys ^= keys << 13;
+        keys ^= keys >> 17;
+        keys ^= keys << 5;
+        keys
+    };
+
+    for _ in 0..FRAMES {
+        let event = next_key_event();
+        let key = event as usize % config::CHIP8_KEY_COUNT;
+        if event & 0x100 != 0 {
+            chip8.key_down(key);
+        } else {
+            chip8.key_up(key);
+        }
+
+        if chip8.run_frame().is_err() {
+            break;
+        }
+    }
+
+    let state = chip8.save_state();
+    let mut restored = Chip8::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+
+    let original = chip8.run_frame().map_err(|err| err.to_string());
+    let copy = restored.run_frame().map_err(|err| err.to_string());
+    assert_eq!(original, copy);
+    assert_eq!(restored.save_state(), chip8.save_state());
+});
@@ -0,0 +1,463 @@
+// The interpreter as a libretro core, to run inside RetroArch and other libretro frontends
+
+// The entry points are only called by the frontend, with the pointers libretro.h describes
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_uint, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chip8oxyde::chip8::audio::{Buzzer, Waveform};
+use chip8oxyde::chip8::quirks::QuirkProfile;
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::config;
+use ffi::*;
+
+mod ffi;
+
+const QUIRKS_VARIABLE: &[u8] = b"chip8oxyde_quirks\0";
+// The first value is the default
+const QUIRKS_VALUES: &[u8] = b"Quirks; modern|cosmac|schip\0";
+
+// RetroPad button of every key. The arrows are on 2, 4, 6 and 8 and A on 5, as most programs
+// use them to move and act
+const JOYPAD_MAP: [c_uint; config::CHIP8_KEY_COUNT] = [
+    RETRO_DEVICE_ID_JOYPAD_B,      // 0
+    RETRO_DEVICE_ID_JOYPAD_L,      // 1
+    RETRO_DEVICE_ID_JOYPAD_UP,     // 2
+    RETRO_DEVICE_ID_JOYPAD_R,      // 3
+    RETRO_DEVICE_ID_JOYPAD_LEFT,   // 4
+    RETRO_DEVICE_ID_JOYPAD_A,      // 5
+    RETRO_DEVICE_ID_JOYPAD_RIGHT,  // 6
+    RETRO_DEVICE_ID_JOYPAD_L2,     // 7
+    RETRO_DEVICE_ID_JOYPAD_DOWN,   // 8
+    RETRO_DEVICE_ID_JOYPAD_R2,     // 9
+    RETRO_DEVICE_ID_JOYPAD_Y,      // A
+    RETRO_DEVICE_ID_JOYPAD_X,      // B
+    RETRO_DEVICE_ID_JOYPAD_L3,     // C
+    RETRO_DEVICE_ID_JOYPAD_R3,     // D
+    RETRO_DEVICE_ID_JOYPAD_SELECT, // E
+    RETRO_DEVICE_ID_JOYPAD_START,  // F
+];
+
+const KEY_NAMES: [&[u8]; config::CHIP8_KEY_COUNT] = [
+    b"Key 0\0", b"Key 1\0", b"Key 2\0", b"Key 3\0", b"Key 4\0", b"Key 5\0", b"Key 6\0", b"Key 7\0",
+    b"Key 8\0", b"Key 9\0", b"Key A\0", b"Key B\0", b"Key C\0", b"Key D\0", b"Key E\0", b"Key F\0",
+];
+
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_sample_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+}
+
+// The loaded program and the machine running it
+struct Core {
+    chip8: Chip8,
+    program: Vec<u8>,
+    buzzer: Buzzer,
+    profile: QuirkProfile,
+    // Set when the program failed, the machine then stays stopped until a reset
+    stopped: bool,
+    audio: Vec<i16>,
+}
+
+impl Core {
+    fn new(program: Vec<u8>, profile: QuirkProfile) -> Option<Self> {
+        let buzzer = Buzzer::new(
+            Waveform::Square { duty: 0.5 },
+            config::CHIP8_SOUND_NOTE_FREQ,
+            config::CHIP8_SOUND_VOLUME,
+            config::CHIP8_SOUND_SAMPLE_RATE as u32,
+        );
+        let mut core = Self {
+            chip8: Chip8::new(),
+            program,
+            buzzer,
+            profile,
+            stopped: false,
+            audio: Vec::new(),
+        };
+
+        if core.reset() {
+            Some(core)
+        } else {
+            None
+        }
+    }
+
+    fn reset(&mut self) -> bool {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        self.chip8 = Chip8::new();
+        // The frontend may write the system RAM between frames, e.g. for cheats, so the core
+        // always uses the interpreter and decodes every instruction from memory
+        self.chip8.set_instruction_cache(false);
+        self.chip8.seed(seed);
+        self.chip8.set_quirks(self.profile.quirks());
+        self.stopped = false;
+
+        self.chip8.load(&self.program).is_ok()
+    }
+
+    fn set_profile(&mut self, profile: QuirkProfile) {
+        self.profile = profile;
+        self.chip8.set_quirks(profile.quirks());
+    }
+
+    fn run(&mut self, callbacks: &Callbacks) {
+        let input_state = match callbacks.input_state {
+            Some(input_state) => input_state,
+            None => return,
+        };
+
+        for (key, button) in JOYPAD_MAP.iter().enumerate() {
+            let retro_key = if key < 10 {
+                RETROK_0 + key as c_uint
+            } else {
+                RETROK_A + key as c_uint - 10
+            };
+            let pressed = unsafe {
+                input_state(0, RETRO_DEVICE_KEYBOARD, 0, retro_key) != 0
+                    || input_state(0, RETRO_DEVICE_JOYPAD, 0, *button) != 0
+            };
+
+            if pressed && !self.chip8.key_states()[key] {
+                self.chip8.key_down(key);
+            } else if !pressed && self.chip8.key_states()[key] {
+                self.chip8.key_up(key);
+            }
+        }
+
+        let sound_playing = !self.stopped
+            && self.chip8.run_frame().unwrap_or_else(|_| {
+                self.stopped = true;
+                false
+            });
+
+        // The buzzer is mono, libretro takes interleaved stereo
+        self.audio.clear();
+        for sample in self.buzzer.render_frame(sound_playing).iter() {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.audio.extend_from_slice(&[sample, sample]);
+        }
+
+        let pixels = self.chip8.screen().to_xrgb8888();
+        unsafe {
+            if let Some(video_refresh) = callbacks.video_refresh {
+                video_refresh(
+                    pixels.as_ptr() as *const c_void,
+                    config::CHIP8_WIDTH,
+                    config::CHIP8_HEIGHT,
+                    config::CHIP8_WIDTH as usize * 4,
+                );
+            }
+            if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+                audio_sample_batch(self.audio.as_ptr(), self.audio.len() / 2);
+            }
+        }
+    }
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> Callbacks {
+    *CALLBACKS.lock().unwrap()
+}
+
+fn core() -> MutexGuard<'static, Option<Core>> {
+    CORE.lock().unwrap()
+}
+
+fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match callbacks().environment {
+        Some(environment) => unsafe { environment(cmd, data) },
+        None => false,
+    }
+}
+
+// The quirk profile picked in the core options
+fn quirk_profile() -> QuirkProfile {
+    let mut variable = RetroVariable {
+        key: QUIRKS_VARIABLE.as_ptr() as *const c_char,
+        value: ptr::null(),
+    };
+
+    if !environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE,
+        &mut variable as *mut _ as *mut c_void,
+    ) || variable.value.is_null()
+    {
+        return QuirkProfile::Modern;
+    }
+
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    value
+        .to_str()
+        .ok()
+        .and_then(QuirkProfile::from_name)
+        .unwrap_or(QuirkProfile::Modern)
+}
+
+fn set_input_descriptors() {
+    let mut descriptors: Vec<RetroInputDescriptor> = JOYPAD_MAP
+        .iter()
+        .zip(KEY_NAMES.iter())
+        .map(|(id, name)| RetroInputDescriptor {
+            port: 0,
+            device: RETRO_DEVICE_JOYPAD,
+            index: 0,
+            id: *id,
+            description: name.as_ptr() as *const c_char,
+        })
+        .collect();
+    descriptors.push(RetroInputDescriptor {
+        port: 0,
+        device: 0,
+        index: 0,
+        id: 0,
+        description: ptr::null(),
+    });
+
+    environment(
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+        descriptors.as_mut_ptr() as *mut c_void,
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: RetroEnvironment) {
+    CALLBACKS.lock().unwrap().environment = Some(callback);
+
+    let mut variables = [
+        RetroVariable {
+            key: QUIRKS_VARIABLE.as_ptr() as *const c_char,
+            value: QUIRKS_VALUES.as_ptr() as *const c_char,
+        },
+        RetroVariable {
+            key: ptr::null(),
+            value: ptr::null(),
+        },
+    ];
+    environment(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr() as *mut c_void,
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: RetroVideoRefresh) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(callback);
+}
+
+// Samples are always sent in batches
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: RetroAudioSample) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: RetroAudioSampleBatch) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: RetroInputPoll) {
+    CALLBACKS.lock().unwrap().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: RetroInputState) {
+    CALLBACKS.lock().unwrap().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *core() = None;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: b"Chip8Oxyde\0".as_ptr() as *const c_char,
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: b"ch8|c8\0".as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: config::CHIP8_WIDTH,
+            base_height: config::CHIP8_HEIGHT,
+            max_width: config::CHIP8_WIDTH,
+            max_height: config::CHIP8_HEIGHT,
+            aspect_ratio: config::CHIP8_WIDTH as f32 / config::CHIP8_HEIGHT as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: 1.0 / config::CHIP8_FRAME_FREQ,
+            sample_rate: config::CHIP8_SOUND_SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = core().as_mut() {
+        core.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    if let Some(input_poll) = callbacks.input_poll {
+        unsafe { input_poll() };
+    }
+
+    let mut updated = false;
+    environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        &mut updated as *mut bool as *mut c_void,
+    );
+    let quirks = if updated { Some(quirk_profile()) } else { None };
+
+    if let Some(core) = core().as_mut() {
+        if let Some(profile) = quirks {
+            core.set_profile(profile);
+        }
+        core.run(&callbacks);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    core()
+        .as_ref()
+        .map_or(0, |core| core.chip8.save_state().len())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let state = match core().as_ref() {
+        Some(core) => core.chip8.save_state(),
+        None => return false,
+    };
+    if size < state.len() {
+        return false;
+    }
+
+    ptr::copy_nonoverlapping(state.as_ptr(), data as *mut u8, state.len());
+
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let state = slice::from_raw_parts(data as *const u8, size);
+
+    match core().as_mut() {
+        Some(core) => {
+            let loaded = core.chip8.load_state(state).is_ok();
+            core.stopped = core.stopped && !loaded;
+            loaded
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        &mut format as *mut c_uint as *mut c_void,
+    ) {
+        return false;
+    }
+    set_input_descriptors();
+
+    let program = slice::from_raw_parts((*game).data as *const u8, (*game).size).to_vec();
+    let profile = quirk_profile();
+
+    let mut core = core();
+    *core = Core::new(program, profile);
+
+    core.is_some()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *core() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+// The 4 KB of memory, for cheats and achievements
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match core().as_mut() {
+        Some(core) if id == RETRO_MEMORY_SYSTEM_RAM => {
+            core.chip8.memory.as_mut_ptr() as *mut c_void
+        }
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match core().as_ref() {
+        Some(_) if id == RETRO_MEMORY_SYSTEM_RAM => config::CHIP8_MEMORY_SIZE,
+        _ => 0,
+    }
+}
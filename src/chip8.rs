@@ -1,45 +1,54 @@
-use rand::Rng;
-use sdl2::audio::AudioDevice;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::EventPump;
-use std::time::{Duration, Instant};
-
 use crate::config;
-use audio::SquareWave;
 use character::{Character, DEFAULT_CHARACTER_SET};
+use engine::Engine;
 use error::Chip8Error;
+use instruction::{BlockCache, Instruction, InstructionCache};
 use keyboard::Keyboard;
+use palette::Palette;
+use quirks::Quirks;
 use registers::Registers;
+use rng::Rng;
 use screen::Screen;
 
 pub mod audio;
 pub mod character;
+pub mod engine;
 pub mod error;
+mod instruction;
 mod keyboard;
-mod registers;
-mod screen;
-
+pub mod palette;
+pub mod quirks;
+pub mod registers;
+mod rng;
+pub mod screen;
+mod state;
+mod trace;
+
+// The interpreter, independent of any frontend. Frontends feed it key presses and call
+// `run_frame` 60 times per second of emulated time
 pub struct Chip8 {
     pub memory: [u8; config::CHIP8_MEMORY_SIZE],
     pub registers: Registers,
     stack: [u16; config::CHIP8_STACK_DEPTH],
     keyboard: Keyboard,
     screen: Screen,
-    canvas: Canvas<Window>,
-    audio_device: AudioDevice<SquareWave>,
-    audio_playing: bool,
-
-    exec_duration: Duration,
-    dt_duration: Duration,
-    st_duration: Duration,
+    rng: Rng,
+    quirks: Quirks,
+
+    // Decoded instructions, invalidated by the writes to memory
+    instructions: InstructionCache,
+    instruction_cache: bool,
+    engine: Engine,
+    blocks: BlockCache,
+
+    // Register receiving the next key pressed, execution stops until then
+    waiting_for_key: Option<usize>,
+    // Fraction of an instruction carried over to the next frame
+    cycle_remainder: f64,
 }
 
 impl Chip8 {
-    pub fn new(canvas: Canvas<Window>, audio_device: AudioDevice<SquareWave>) -> Self {
+    pub fn new() -> Self {
         let mut memory = [0; config::CHIP8_MEMORY_SIZE];
         memory[..config::CHIP8_CHARACTER_SET_SIZE].copy_from_slice(&DEFAULT_CHARACTER_SET[..]);
 
@@ -49,13 +58,16 @@ impl Chip8 {
             stack: [0; config::CHIP8_STACK_DEPTH],
             keyboard: Keyboard::new(),
             screen: Screen::new(),
-            canvas,
-            audio_device,
-            audio_playing: false,
+            rng: Rng::new(0),
+            quirks: Quirks::default(),
 
-            exec_duration: Duration::from_secs(0),
-            dt_duration: Duration::from_secs(0),
-            st_duration: Duration::from_secs(0),
+            instructions: InstructionCache::new(config::CHIP8_MEMORY_SIZE),
+            instruction_cache: true,
+            engine: Engine::Interpreter,
+            blocks: BlockCache::new(config::CHIP8_MEMORY_SIZE),
+
+            waiting_for_key: None,
+            cycle_remainder: 0.0,
         }
     }
 
@@ -66,67 +78,149 @@ impl Chip8 {
 
         let start_addr = config::CHIP8_PROGRAM_LOAD_ADDRESS;
         self.memory[start_addr..start_addr + buf.len()].copy_from_slice(buf);
+        self.flush_instruction_cache();
 
         self.registers.pc = start_addr as u16;
 
         Ok(())
     }
 
-    pub fn run(&mut self, event_pump: &mut EventPump) {
-        let texture_creator = self.canvas.texture_creator();
-        let mut texture = texture_creator
-            .create_texture_streaming(
-                PixelFormatEnum::RGB24,
-                config::CHIP8_WIDTH,
-                config::CHIP8_HEIGHT,
-            )
-            .unwrap();
+    // Seed of the random numbers returned by RND, the same seed always gives the same run
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Decoding every instruction on each execution is slower, but lets `memory` be written
+    // directly between steps. Without the cache, the block engines interpret one instruction at
+    // a time too
+    pub fn set_instruction_cache(&mut self, enabled: bool) {
+        self.instruction_cache = enabled;
+        self.instructions.clear();
+        self.blocks.clear();
+    }
+
+    // Forgets the decoded instructions and blocks, needed after writing to `memory` directly
+    // while the instruction cache is enabled or with the block engines
+    pub fn flush_instruction_cache(&mut self) {
+        self.instructions.clear();
+        self.blocks.clear();
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+        self.blocks.clear();
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.screen.set_palette(palette);
+    }
+
+    pub fn set_persistence(&mut self, enabled: bool) {
+        self.screen.set_persistence(enabled);
+    }
+
+    pub fn key_states(&self) -> &[bool] {
+        self.keyboard.key_states()
+    }
 
-        'running: loop {
-            let loop_start = Instant::now();
-
-            self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-            self.canvas.clear();
-
-            for event in event_pump.poll_iter() {
-                match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => break 'running,
-                    Event::KeyDown {
-                        keycode: Some(key), ..
-                    } => self.keyboard.key_down(key),
-
-                    Event::KeyUp {
-                        keycode: Some(key), ..
-                    } => self.keyboard.key_up(key),
-                    _ => {}
+    pub fn key_down(&mut self, key: usize) {
+        self.keyboard.press(key);
+
+        if let Some(x) = self.waiting_for_key.take() {
+            self.registers.v[x] = key as u8;
+        }
+    }
+
+    pub fn key_up(&mut self, key: usize) {
+        self.keyboard.release(key);
+    }
+
+    // Presses the given keys and releases the others
+    pub fn hold_keys(&mut self, keys: &[usize]) {
+        for key in 0..config::CHIP8_KEY_COUNT {
+            let held = keys.contains(&key);
+            if held && !self.keyboard.is_key_down(key) {
+                self.key_down(key);
+            } else if !held && self.keyboard.is_key_down(key) {
+                self.key_up(key);
+            }
+        }
+    }
+
+    // Advance emulated time by one frame: execute the frame's instructions, then update the
+    // timers and the display persistence. Returns whether the buzzer sounded during the frame
+    pub fn run_frame(&mut self) -> Result<bool, Chip8Error> {
+        let cycle_count = self.frame_cycles();
+        self.run_instructions(cycle_count)?;
+
+        Ok(self.end_frame())
+    }
+
+    // Like `run_frame`, calling `trace` with the machine state before every executed instruction
+    pub fn run_frame_traced(
+        &mut self,
+        trace: &mut dyn FnMut(&Chip8) -> Result<(), Chip8Error>,
+    ) -> Result<bool, Chip8Error> {
+        // Always interpreted, one instruction at a time
+        for _ in 0..self.frame_cycles() {
+            if self.waiting_for_key.is_none() {
+                trace(self)?;
+            }
+            self.step()?;
+        }
+
+        Ok(self.end_frame())
+    }
+
+    // Executes `count` instructions with the selected engine, without advancing the timers.
+    // Stops early when an instruction waits for a key
+    pub fn run_instructions(&mut self, count: usize) -> Result<(), Chip8Error> {
+        match self.engine {
+            Engine::Interpreter => {
+                for _ in 0..count {
+                    if self.waiting_for_key.is_some() {
+                        break;
+                    }
+                    self.step()?;
                 }
+                Ok(())
             }
+            Engine::Blocks => self.run_blocks(count),
+            Engine::Lockstep => self.run_lockstep(count),
+        }
+    }
 
-            // Draw frame on a SDL texture
-            texture
-                .update(
-                    None,
-                    self.screen.pixel_colors(),
-                    config::CHIP8_WIDTH as usize * 3,
-                )
-                .unwrap();
+    // Instructions executed in the next frame
+    fn frame_cycles(&mut self) -> usize {
+        let cycles = config::CHIP8_FRAME_FREQ / config::CHIP8_EXEC_FREQ + self.cycle_remainder;
+        let cycle_count = cycles.floor();
+        self.cycle_remainder = cycles - cycle_count;
 
-            // Draw frame texture to window
-            self.canvas.copy(&texture, None, None).unwrap();
-            self.canvas.present();
+        cycle_count as usize
+    }
 
-            self.exec(event_pump);
-            
-            let time_passed = Instant::now() - loop_start;
+    // Timers and display persistence, returns whether the buzzer sounded during the frame
+    fn end_frame(&mut self) -> bool {
+        let sound_playing = self.registers.st > 0;
 
-            self.update_exec_timer(time_passed);
-            self.update_sound_timer(time_passed);
-            self.update_delay_timer(time_passed);
+        if self.registers.dt > 0 {
+            self.registers.dt -= 1;
+        }
+        if self.registers.st > 0 {
+            self.registers.st -= 1;
         }
+
+        self.screen.fade();
+
+        sound_playing
     }
 
     #[allow(dead_code)]
@@ -135,144 +229,117 @@ impl Chip8 {
             x,
             y,
             &self.memory[c as usize..c as usize + config::CHIP8_DEFAULT_SPRITE_HEIGHT as usize],
+            false,
         );
     }
 
-    fn update_exec_timer(&mut self, delta: Duration) {
-        self.exec_duration += delta;
+    // Addresses wrap around the end of memory
+    fn read(&self, addr: usize) -> u8 {
+        self.memory[addr % config::CHIP8_MEMORY_SIZE]
     }
 
-    fn update_delay_timer(&mut self, delta: Duration) {
-        self.dt_duration += delta;
-        if self.dt_duration.as_secs_f64() >= config::CHIP8_DELAY_TIMER_FREQ && self.registers.dt > 0
-        {
-            self.dt_duration = Duration::from_secs(0);
-            self.registers.dt -= 1;
-        }
+    fn write(&mut self, addr: usize, val: u8) {
+        let addr = addr % config::CHIP8_MEMORY_SIZE;
+        self.memory[addr] = val;
+        // Self-modifying code
+        self.instructions.invalidate(addr);
+        self.blocks.invalidate(addr);
     }
 
-    fn update_sound_timer(&mut self, delta: Duration) {
-        self.st_duration += delta;
+    fn get_u16(&self, addr: u16) -> u16 {
+        (self.read(addr as usize) as u16) << 8 | (self.read(addr as usize + 1) as u16)
+    }
 
-        if self.audio_playing && self.registers.st == 0 {
-            self.audio_playing = false;
-            self.audio_device.pause();
+    // Execute one instruction, does nothing while waiting for a key press
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        if self.waiting_for_key.is_some() {
+            return Ok(());
         }
 
-        if self.st_duration.as_secs_f64() >= config::CHIP8_SOUND_TIMER_FREQ && self.registers.st > 0
-        {
-            self.st_duration = Duration::from_secs(0);
+        let instruction = self.fetch();
+        self.registers.pc = self.registers.pc.wrapping_add(2);
 
-            if !self.audio_playing {
-                self.audio_playing = true;
-                self.audio_device.resume();
-            }
+        self.execute(instruction)
+    }
 
-            self.registers.st -= 1;
+    fn fetch(&mut self) -> Instruction {
+        if !self.instruction_cache {
+            return Instruction::decode(self.get_u16(self.registers.pc));
         }
-    }
 
-    fn get_u16(&self, addr: u16) -> u16 {
-        (self.memory[addr as usize] as u16) << 8 | (self.memory[addr as usize + 1] as u16)
+        let addr = self.registers.pc as usize % config::CHIP8_MEMORY_SIZE;
+        match self.instructions.get(addr) {
+            Some(instruction) => instruction,
+            None => {
+                let instruction = Instruction::decode(self.get_u16(self.registers.pc));
+                self.instructions.insert(addr, instruction);
+                instruction
+            }
+        }
     }
 
-    fn exec(&mut self, event_pump: &mut EventPump) {
-        if self.exec_duration.as_secs_f64() >= config::CHIP8_EXEC_FREQ {
-            self.exec_duration = Duration::from_secs(0);
+    fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        use Instruction::*;
+
+        match instruction {
+            Cls => self.cls(),
+            Ret => return self.ret(),
+            Jp(nnn) => self.jp(nnn),
+            Call(nnn) => return self.call(nnn),
+            SeVxByte(x, kk) => self.se_vx_byte(x as usize, kk),
+            SneVxByte(x, kk) => self.sne_vx_byte(x as usize, kk),
+            SeVxVy(x, y) => self.se_vx_vy(x as usize, y as usize),
+            LdVxByte(x, kk) => self.ld_vx_byte(x as usize, kk),
+            AddVxByte(x, kk) => self.add_vx_byte(x as usize, kk),
+            LdVxVy(x, y) => self.ld_vx_vy(x as usize, y as usize),
+            OrVxVy(x, y) => self.or_vx_vy(x as usize, y as usize),
+            AndVxVy(x, y) => self.and_vx_vy(x as usize, y as usize),
+            XorVxVy(x, y) => self.xor_vx_vy(x as usize, y as usize),
+            AddVxVy(x, y) => self.add_vx_vy(x as usize, y as usize),
+            SubVxVy(x, y) => self.sub_vx_vy(x as usize, y as usize),
+            ShrVx(x, y) => self.shr_vx(x as usize, y as usize),
+            SubnVxVy(x, y) => self.subn_vx_vy(x as usize, y as usize),
+            ShlVx(x, y) => self.shl_vx(x as usize, y as usize),
+            SneVxVy(x, y) => self.sne_vx_vy(x as usize, y as usize),
+            LdI(nnn) => self.ld_i(nnn),
+            JpV0(nnn) => self.jp_v0(nnn),
+            Rnd(x, kk) => self.rnd(x as usize, kk),
+            Drw(x, y, n) => self.drw(x as usize, y as usize, n as usize),
+            Skp(x) => self.skp(x as usize),
+            Sknp(x) => self.sknp(x as usize),
+            LdVxDt(x) => self.ld_vx_dt(x as usize),
+            LdVxK(x) => self.ld_vx_k(x as usize),
+            LdDtVx(x) => self.ld_dt_vx(x as usize),
+            LdStVx(x) => self.ld_st_vx(x as usize),
+            AddIVx(x) => self.add_i_vx(x as usize),
+            LdFVx(x) => self.ld_f_vx(x as usize),
+            LdBVx(x) => self.ld_b_vx(x as usize),
+            LdIVx(x) => self.ld_i_vx(x as usize),
+            LdVxI(x) => self.ld_vx_i(x as usize),
+            Illegal(opcode) => return Err(Chip8Error::IllegalOpcode(opcode)),
+        }
 
-            let opcode = self.get_u16(self.registers.pc);
-            self.registers.pc += 2;
+        Ok(())
+    }
 
-            match opcode {
-                0x00E0 => self.cls(),
-                0x00EE => self.ret(),
-                opcode => self.decode_byte(opcode, event_pump),
-            }
+    fn push(&mut self, val: u16) -> Result<(), Chip8Error> {
+        if self.registers.sp as usize >= config::CHIP8_STACK_DEPTH {
+            return Err(Chip8Error::StackOverflow);
         }
-    }
 
-    fn decode_byte(&mut self, opcode: u16, event_pump: &mut EventPump) {
-        let nnn = opcode & 0x0FFF;
-        let n = (opcode & 0x000F) as usize;
-        let x = ((opcode >> 8) & 0x000F) as usize;
-        let y = ((opcode >> 4) & 0x000F) as usize;
-        let kk = (opcode & 0x00FF) as u8;
-        match opcode & 0xF000 {
-            0x1000 => self.jp(nnn),
-            0x2000 => self.call(nnn),
-            0x3000 => self.se_vx_byte(x, kk),
-            0x4000 => self.sne_vx_byte(x, kk),
-            0x5000 if opcode & 0xF == 0x0 => self.se_vx_vy(x, y),
-            0x6000 => self.ld_vx_byte(x, kk),
-            0x7000 => self.add_vx_byte(x, kk),
-            0x8000 => match opcode & 0x000F {
-                0x0000 => self.ld_vx_vy(x, y),
-                0x0001 => self.or_vx_vy(x, y),
-                0x0002 => self.and_vx_vy(x, y),
-                0x0003 => self.xor_vx_vy(x, y),
-                0x0004 => self.add_vx_vy(x, y),
-                0x0005 => self.sub_vx_vy(x, y),
-                0x0006 => self.shr_vx(x),
-                0x0007 => self.subn_vx_vy(x, y),
-                0x000E => self.shl_vx(x),
-                _ => panic!("Illegal opcode"),
-            },
-            0x9000 if opcode & 0xF == 0x0 => self.sne_vx_vy(x, y),
-            0xA000 => self.ld_i(nnn),
-            0xB000 => self.jp_v0(nnn),
-            0xC000 => self.rnd(x, kk),
-            0xD000 => self.drw(x, y, n),
-            0xE000 => match opcode & 0x00FF {
-                0x9E => self.skp(x),
-                0xA1 => self.sknp(x),
-                _ => panic!("Illegal opcode"),
-            },
-            0xF000 => match opcode & 0x00FF {
-                0x07 => self.ld_vx_dt(x),
-                0x0A => self.ld_vx_k(x, event_pump),
-                0x15 => self.ld_dt_vx(x),
-                0x18 => self.ld_st_vx(x),
-                0x1E => self.add_i_vx(x),
-                0x29 => self.ld_f_vx(x),
-                0x33 => self.ld_b_vx(x),
-                0x55 => self.ld_i_vx(x),
-                0x65 => self.ld_vx_i(x),
-                _ => panic!("Illegal opcode"),
-            },
-            _ => panic!("Illegal opcode"),
-        }
-    }
-
-    fn push(&mut self, val: u16) {
         self.stack[self.registers.sp as usize] = val;
         self.registers.sp += 1;
+
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
-        self.registers.sp -= 1;
-        self.stack[self.registers.sp as usize]
-    }
-
-    fn wait_for_key(&self, event_pump: &mut EventPump) -> usize {
-        for event in event_pump.wait_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => std::process::exit(0),
-                Event::KeyDown {
-                    keycode: Some(key), ..
-                } => {
-                    if let Some(key) = self.keyboard.map_key(key) {
-                        return *key;
-                    }
-                }
-                _ => {}
-            }
+    fn pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.registers.sp == 0 {
+            return Err(Chip8Error::StackUnderflow);
         }
 
-        unreachable!();
+        self.registers.sp -= 1;
+        Ok(self.stack[self.registers.sp as usize])
     }
 
     // CLS: Clear the screen
@@ -281,8 +348,10 @@ impl Chip8 {
     }
 
     // RET: Return from subroutine
-    fn ret(&mut self) {
-        self.registers.pc = self.pop();
+    fn ret(&mut self) -> Result<(), Chip8Error> {
+        self.registers.pc = self.pop()?;
+
+        Ok(())
     }
 
     // 0x1nnn - JP: Jump to location addr
@@ -291,9 +360,11 @@ impl Chip8 {
     }
 
     // 0x2nnn CALL: Call subroutine at addr
-    fn call(&mut self, nnn: u16) {
-        self.push(self.registers.pc);
+    fn call(&mut self, nnn: u16) -> Result<(), Chip8Error> {
+        self.push(self.registers.pc)?;
         self.registers.pc = nnn;
+
+        Ok(())
     }
 
     // 0x3xkk - SE Vx, byte: Skip next instruction if Vx == kk
@@ -335,47 +406,72 @@ impl Chip8 {
     // 0x8xy1 - OR Vx, Vy: Bitwise OR on Vx and Vy, store result in Vx
     fn or_vx_vy(&mut self, x: usize, y: usize) {
         self.registers.v[x] |= self.registers.v[y];
+        self.reset_vf();
     }
 
     // 0x8xy2 - AND Vx, Vy: Bitwise AND on Vx and Vy, store result in Vx
     fn and_vx_vy(&mut self, x: usize, y: usize) {
         self.registers.v[x] &= self.registers.v[y];
+        self.reset_vf();
     }
 
     // 0x8xy3 - XOR Vx, Vy: Bitwise XOR on Vx and Vy, store result in Vx
     fn xor_vx_vy(&mut self, x: usize, y: usize) {
         self.registers.v[x] ^= self.registers.v[y];
+        self.reset_vf();
     }
 
-    // 0x8xy4 - ADD Vx, Vy: Add Vx and Vy, store result in Vx, VF is set to carry bit
+    // 0x8xy4 - ADD Vx, Vy: Add Vx and Vy, store result in Vx, VF is set to carry bit.
+    // Like the other flag-setting instructions, VF is written last so it holds the flag when
+    // it is also Vx
     fn add_vx_vy(&mut self, x: usize, y: usize) {
         let result = self.registers.v[x] as u16 + self.registers.v[y] as u16;
         self.registers.v[x] = result as u8;
         self.registers.v[0xF] = (result > 0xFF) as u8;
     }
 
-    // 0x8xy5 - SUB Vx, Vy: Sub Vy from Vx, store result in Vx, VF is set to Vx > Vy
+    // 0x8xy5 - SUB Vx, Vy: Sub Vy from Vx, store result in Vx, VF is set to Vx >= Vy
     fn sub_vx_vy(&mut self, x: usize, y: usize) {
-        self.registers.v[0xF] = (self.registers.v[x] > self.registers.v[y]) as u8;
-        self.registers.v[x] = self.registers.v[x].wrapping_sub(self.registers.v[y]);
+        let (vx, vy) = (self.registers.v[x], self.registers.v[y]);
+        self.registers.v[x] = vx.wrapping_sub(vy);
+        self.registers.v[0xF] = (vx >= vy) as u8;
     }
 
     // 0x8xy6 - SHR Vx: Bitwise shift right by 1, VF is set to lowest bit
-    fn shr_vx(&mut self, x: usize) {
-        self.registers.v[0xF] = self.registers.v[x] & 0x01;
-        self.registers.v[x] >>= 1;
+    fn shr_vx(&mut self, x: usize, y: usize) {
+        self.load_shift_operand(x, y);
+        let vx = self.registers.v[x];
+        self.registers.v[x] = vx >> 1;
+        self.registers.v[0xF] = vx & 0x01;
     }
 
-    // 0x8xy7 - SUBN Vx, Vy: Sub Vx from Vy, store result in Vx, VF is set to Vy > Vx
+    // 0x8xy7 - SUBN Vx, Vy: Sub Vx from Vy, store result in Vx, VF is set to Vy >= Vx
     fn subn_vx_vy(&mut self, x: usize, y: usize) {
-        self.registers.v[0xF] = (self.registers.v[y] > self.registers.v[x]) as u8;
-        self.registers.v[x] = self.registers.v[y].wrapping_sub(self.registers.v[x]);
+        let (vx, vy) = (self.registers.v[x], self.registers.v[y]);
+        self.registers.v[x] = vy.wrapping_sub(vx);
+        self.registers.v[0xF] = (vy >= vx) as u8;
     }
 
     // 0x8xyE - SHL Vx: Bitwise shift left by 1, VF is set to highest bit
-    fn shl_vx(&mut self, x: usize) {
-        self.registers.v[0xF] = self.registers.v[x] & 0x80;
-        self.registers.v[x] <<= 1;
+    fn shl_vx(&mut self, x: usize, y: usize) {
+        self.load_shift_operand(x, y);
+        let vx = self.registers.v[x];
+        self.registers.v[x] = vx << 1;
+        self.registers.v[0xF] = vx >> 7;
+    }
+
+    // VF reset quirk of the logical operations
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers.v[0xF] = 0;
+        }
+    }
+
+    // Shift quirk: the shifts operate on Vy
+    fn load_shift_operand(&mut self, x: usize, y: usize) {
+        if self.quirks.shift_vy {
+            self.registers.v[x] = self.registers.v[y];
+        }
     }
 
     // 0x9xy0 - SNE Vx, Vy: Skip next instruction if Vx != Vy
@@ -390,24 +486,35 @@ impl Chip8 {
         self.registers.i = nnn;
     }
 
-    // 0xBnnn - JP V0, addr: Jump to location addr + V0
+    // 0xBnnn - JP V0, addr: Jump to location addr + V0, or addr + Vx with the jump quirk
     fn jp_v0(&mut self, nnn: u16) {
-        self.registers.pc = nnn.wrapping_add(self.registers.v[0x0] as u16);
+        let offset = if self.quirks.jump_vx {
+            self.registers.v[(nnn >> 8) as usize]
+        } else {
+            self.registers.v[0x0]
+        };
+
+        self.registers.pc = nnn.wrapping_add(offset as u16);
     }
 
     // 0xCxkk - RND Vx, byte: Generate random number between 0 and 255, then bitwise AND with kk
     fn rnd(&mut self, x: usize, kk: u8) {
-        let rng: u16 = rand::thread_rng().gen_range(0..=255);
-        self.registers.v[x] = rng as u8 & kk;
+        self.registers.v[x] = self.rng.next_u8() & kk;
     }
 
     // 0xDxyn - DRW Vx, Vy, nibble: Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
     fn drw(&mut self, x: usize, y: usize, n: usize) {
         let sprite_addr = self.registers.i as usize;
+        let mut sprite = [0; 15];
+        for (offset, row) in sprite[..n].iter_mut().enumerate() {
+            *row = self.read(sprite_addr + offset);
+        }
+
         let collision = self.screen.draw_sprite(
             self.registers.v[x] as usize,
             self.registers.v[y] as usize,
-            &self.memory[sprite_addr..sprite_addr + n],
+            &sprite[..n],
+            self.quirks.clipping,
         );
 
         self.registers.v[0xF] = collision as u8;
@@ -415,28 +522,32 @@ impl Chip8 {
 
     // 0xEx9E - SKP Vx: Skip next instruction if key with the value of Vx is pressed
     fn skp(&mut self, x: usize) {
-        if self.keyboard.is_key_down(self.registers.v[x] as usize) {
+        if self.keyboard.is_key_down(self.key_in(x)) {
             self.registers.pc = self.registers.pc.wrapping_add(2);
         }
     }
 
     // 0xExA1 - SKNP Vx: Skip next instruction if key with the value of Vx is not pressed
     fn sknp(&mut self, x: usize) {
-        if !self.keyboard.is_key_down(self.registers.v[x] as usize) {
+        if !self.keyboard.is_key_down(self.key_in(x)) {
             self.registers.pc = self.registers.pc.wrapping_add(2);
         }
     }
 
+    // Only the low nibble of Vx selects a key
+    fn key_in(&self, x: usize) -> usize {
+        (self.registers.v[x] & 0x0F) as usize
+    }
+
     // 0xFx07 - LD Vx, DT: Set Vx = delay timer value
     fn ld_vx_dt(&mut self, x: usize) {
         self.registers.v[x] = self.registers.dt;
     }
 
     // 0xFx0A - LD Vx, K: Wait for a key press, store the value of the key in Vx
-    fn ld_vx_k(&mut self, x: usize, event_pump: &mut EventPump) {
-        let key = self.wait_for_key(event_pump);
-
-        self.registers.v[x] = key as u8;
+    fn ld_vx_k(&mut self, x: usize) {
+        // Execution resumes in key_down
+        self.waiting_for_key = Some(x);
     }
 
     // 0xFx15 - LD DT, Vx: Set delay timer = Vx
@@ -447,10 +558,6 @@ impl Chip8 {
     // 0xFx18 - LD ST, Vx: Set sound timer = Vx
     fn ld_st_vx(&mut self, x: usize) {
         self.registers.st = self.registers.v[x];
-        if self.registers.st > 0 {
-            self.audio_playing = true;
-            self.audio_device.resume();
-        }
     }
 
     // 0xFx1E ADD I, Vx: The values of I and Vx are added, and the results are stored in I
@@ -471,20 +578,573 @@ impl Chip8 {
         let tens = value / 10 % 10;
         let hundreds = value / 100;
 
-        self.memory[self.registers.i as usize] = hundreds;
-        self.memory[self.registers.i as usize + 1] = tens;
-        self.memory[self.registers.i as usize + 2] = units;
+        let addr = self.registers.i as usize;
+        self.write(addr, hundreds);
+        self.write(addr + 1, tens);
+        self.write(addr + 2, units);
     }
 
     // 0xFx55 LD [I], Vx: Store registers V0 through Vx in memory starting at location I
     fn ld_i_vx(&mut self, x: usize) {
         let start_loc = self.registers.i as usize;
-        self.memory[start_loc..=start_loc + x].copy_from_slice(&self.registers.v[..=x]);
+        for offset in 0..=x {
+            self.write(start_loc + offset, self.registers.v[offset]);
+        }
+        self.increment_i(x);
     }
 
     // 0xFx65 LD Vx, [I]: Read registers V0 through Vx from memory starting at location I
     fn ld_vx_i(&mut self, x: usize) {
         let start_loc = self.registers.i as usize;
-        self.registers.v[..=x].copy_from_slice(&self.memory[start_loc..=start_loc + x]);
+        for offset in 0..=x {
+            self.registers.v[offset] = self.read(start_loc + offset);
+        }
+        self.increment_i(x);
+    }
+
+    // Load/store quirk: I is left after the last register
+    fn increment_i(&mut self, x: usize) {
+        if self.quirks.load_store_increment {
+            self.registers.i = self.registers.i.wrapping_add(x as u16 + 1);
+        }
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_START: u16 = config::CHIP8_PROGRAM_LOAD_ADDRESS as u16;
+
+    // Registers set before an instruction, and expected after it
+    type RegisterValues = &'static [(usize, u8)];
+
+    fn chip8_with(opcode: u16, registers: RegisterValues) -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.load(&opcode.to_be_bytes()).unwrap();
+        for (register, value) in registers.iter() {
+            chip8.registers.v[*register] = *value;
+        }
+
+        chip8
+    }
+
+    fn assert_registers(chip8: &Chip8, opcode: u16, expected: RegisterValues) {
+        for (register, value) in expected.iter() {
+            assert_eq!(
+                chip8.registers.v[*register], *value,
+                "V{:X} after {:04X}",
+                register, opcode
+            );
+        }
+    }
+
+    #[test]
+    fn register_instructions() {
+        let cases: &[(u16, RegisterValues, RegisterValues)] = &[
+            // 6xkk
+            (0x6A42, &[], &[(0xA, 0x42)]),
+            // 7xkk wraps around and leaves VF alone
+            (
+                0x7102,
+                &[(0x1, 0xFF), (0xF, 0x55)],
+                &[(0x1, 0x01), (0xF, 0x55)],
+            ),
+            // 8xy0
+            (0x8120, &[(0x2, 0x37)], &[(0x1, 0x37), (0x2, 0x37)]),
+            // 8xy1, 8xy2, 8xy3 leave VF alone with the default quirks
+            (
+                0x8121,
+                &[(0x1, 0x0C), (0x2, 0x0A), (0xF, 0x55)],
+                &[(0x1, 0x0E), (0xF, 0x55)],
+            ),
+            (0x8122, &[(0x1, 0x0C), (0x2, 0x0A)], &[(0x1, 0x08)]),
+            (0x8123, &[(0x1, 0x0C), (0x2, 0x0A)], &[(0x1, 0x06)]),
+            // 8xy4
+            (
+                0x8124,
+                &[(0x1, 0x01), (0x2, 0x02)],
+                &[(0x1, 0x03), (0xF, 0)],
+            ),
+            (
+                0x8124,
+                &[(0x1, 0xFF), (0x2, 0x02)],
+                &[(0x1, 0x01), (0xF, 1)],
+            ),
+            (
+                0x8124,
+                &[(0x1, 0xFF), (0x2, 0x01)],
+                &[(0x1, 0x00), (0xF, 1)],
+            ),
+            (0x8F24, &[(0xF, 0xFF), (0x2, 0x02)], &[(0xF, 1)]),
+            (
+                0x81F4,
+                &[(0x1, 0xFF), (0xF, 0x01)],
+                &[(0x1, 0x00), (0xF, 1)],
+            ),
+            // 8xy5, VF is 1 when there is no borrow
+            (
+                0x8125,
+                &[(0x1, 0x05), (0x2, 0x03)],
+                &[(0x1, 0x02), (0xF, 1)],
+            ),
+            (
+                0x8125,
+                &[(0x1, 0x03), (0x2, 0x05)],
+                &[(0x1, 0xFE), (0xF, 0)],
+            ),
+            (
+                0x8125,
+                &[(0x1, 0x05), (0x2, 0x05)],
+                &[(0x1, 0x00), (0xF, 1)],
+            ),
+            (0x8F25, &[(0xF, 0x05), (0x2, 0x03)], &[(0xF, 1)]),
+            (
+                0x81F5,
+                &[(0x1, 0x01), (0xF, 0x02)],
+                &[(0x1, 0xFF), (0xF, 0)],
+            ),
+            // 8xy6
+            (0x8126, &[(0x1, 0x03)], &[(0x1, 0x01), (0xF, 1)]),
+            (0x8126, &[(0x1, 0x02)], &[(0x1, 0x01), (0xF, 0)]),
+            (0x8F26, &[(0xF, 0x02)], &[(0xF, 0)]),
+            (0x8F26, &[(0xF, 0x03)], &[(0xF, 1)]),
+            // 8xy7, VF is 1 when there is no borrow
+            (
+                0x8127,
+                &[(0x1, 0x03), (0x2, 0x05)],
+                &[(0x1, 0x02), (0xF, 1)],
+            ),
+            (
+                0x8127,
+                &[(0x1, 0x05), (0x2, 0x03)],
+                &[(0x1, 0xFE), (0xF, 0)],
+            ),
+            (
+                0x8127,
+                &[(0x1, 0x05), (0x2, 0x05)],
+                &[(0x1, 0x00), (0xF, 1)],
+            ),
+            (0x8F27, &[(0xF, 0x03), (0x2, 0x05)], &[(0xF, 1)]),
+            // 8xyE sets VF to the bit shifted out, as 0 or 1
+            (0x812E, &[(0x1, 0x81)], &[(0x1, 0x02), (0xF, 1)]),
+            (0x812E, &[(0x1, 0x41)], &[(0x1, 0x82), (0xF, 0)]),
+            (0x8F2E, &[(0xF, 0x80)], &[(0xF, 1)]),
+            // Cxkk with a zero mask
+            (0xC100, &[(0x1, 0xFF)], &[(0x1, 0x00)]),
+            // Fx07
+            (0xF107, &[], &[(0x1, 0x00)]),
+        ];
+
+        for (opcode, before, after) in cases.iter() {
+            let mut chip8 = chip8_with(*opcode, before);
+            chip8.step().unwrap();
+
+            assert_registers(&chip8, *opcode, after);
+            assert_eq!(
+                chip8.registers.pc,
+                PROGRAM_START + 2,
+                "PC after {:04X}",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn skip_instructions() {
+        let cases: &[(u16, RegisterValues, bool)] = &[
+            // 3xkk
+            (0x3142, &[(0x1, 0x42)], true),
+            (0x3142, &[(0x1, 0x41)], false),
+            // 4xkk
+            (0x4142, &[(0x1, 0x42)], false),
+            (0x4142, &[(0x1, 0x41)], true),
+            // 5xy0
+            (0x5120, &[(0x1, 0x42), (0x2, 0x42)], true),
+            (0x5120, &[(0x1, 0x42), (0x2, 0x43)], false),
+            // 9xy0
+            (0x9120, &[(0x1, 0x42), (0x2, 0x42)], false),
+            (0x9120, &[(0x1, 0x42), (0x2, 0x43)], true),
+        ];
+
+        for (opcode, before, skipped) in cases.iter() {
+            let mut chip8 = chip8_with(*opcode, before);
+            chip8.step().unwrap();
+
+            let expected_pc = PROGRAM_START + if *skipped { 4 } else { 2 };
+            assert_eq!(chip8.registers.pc, expected_pc, "PC after {:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn key_instructions() {
+        for pressed in [false, true].iter() {
+            for (opcode, skip_when_pressed) in [(0xE19E, true), (0xE1A1, false)].iter() {
+                let mut chip8 = chip8_with(*opcode, &[(0x1, 0xA)]);
+                if *pressed {
+                    chip8.key_down(0xA);
+                }
+                chip8.step().unwrap();
+
+                let skipped = *pressed == *skip_when_pressed;
+                let expected_pc = PROGRAM_START + if skipped { 4 } else { 2 };
+                assert_eq!(chip8.registers.pc, expected_pc, "PC after {:04X}", opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn wait_for_key() {
+        let mut chip8 = chip8_with(0xF10A, &[]);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, PROGRAM_START + 2);
+
+        chip8.key_down(0x7);
+        assert_eq!(chip8.registers.v[0x1], 0x7);
+        assert!(chip8.waiting_for_key.is_none());
+    }
+
+    #[test]
+    fn flow_instructions() {
+        // 1nnn
+        let mut chip8 = chip8_with(0x1234, &[]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x234);
+
+        // 2nnn then 00EE
+        let mut chip8 = chip8_with(0x2300, &[]);
+        chip8.memory[0x300..0x302].copy_from_slice(&[0x00, 0xEE]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x300);
+        assert_eq!(chip8.registers.sp, 1);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, PROGRAM_START + 2);
+        assert_eq!(chip8.registers.sp, 0);
+
+        // Bnnn
+        let mut chip8 = chip8_with(0xB300, &[(0x0, 0x04), (0x3, 0x08)]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.pc, 0x304);
+    }
+
+    #[test]
+    fn index_instructions() {
+        // Annn
+        let mut chip8 = chip8_with(0xA123, &[]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.i, 0x123);
+
+        // Fx1E
+        let mut chip8 = chip8_with(0xF11E, &[(0x1, 0x10)]);
+        chip8.registers.i = 0x0FF8;
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.i, 0x1008);
+
+        // Fx29
+        let mut chip8 = chip8_with(0xF129, &[(0x1, 0xA)]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.i, Character::A as u16);
+    }
+
+    #[test]
+    fn timer_instructions() {
+        // Fx15 then Fx07
+        let mut chip8 = chip8_with(0xF115, &[(0x1, 0x80)]);
+        chip8.memory[0x202..0x204].copy_from_slice(&[0xF2, 0x07]);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.dt, 0x80);
+        assert_eq!(chip8.registers.v[0x2], 0x80);
+
+        // Fx18
+        let mut chip8 = chip8_with(0xF118, &[(0x1, 0x20)]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.st, 0x20);
+    }
+
+    #[test]
+    fn memory_instructions() {
+        // Fx33
+        let mut chip8 = chip8_with(0xF133, &[(0x1, 254)]);
+        chip8.registers.i = 0x300;
+        chip8.step().unwrap();
+        assert_eq!(chip8.memory[0x300..0x303], [2, 5, 4]);
+        assert_eq!(chip8.registers.i, 0x300);
+
+        // Fx55
+        let mut chip8 = chip8_with(
+            0xF255,
+            &[(0x0, 0x11), (0x1, 0x22), (0x2, 0x33), (0x3, 0x44)],
+        );
+        chip8.registers.i = 0x300;
+        chip8.step().unwrap();
+        assert_eq!(chip8.memory[0x300..0x304], [0x11, 0x22, 0x33, 0x00]);
+        assert_eq!(chip8.registers.i, 0x300);
+
+        // Fx65
+        let mut chip8 = chip8_with(0xF265, &[(0x3, 0x44)]);
+        chip8.registers.i = 0x300;
+        chip8.memory[0x300..0x304].copy_from_slice(&[0x11, 0x22, 0x33, 0x55]);
+        chip8.step().unwrap();
+        assert_registers(
+            &chip8,
+            0xF265,
+            &[(0x0, 0x11), (0x1, 0x22), (0x2, 0x33), (0x3, 0x44)],
+        );
+        assert_eq!(chip8.registers.i, 0x300);
+    }
+
+    #[test]
+    fn display_instructions() {
+        // Dxyn draws the font sprite of 0, then erases it and reports the collision
+        let mut chip8 = chip8_with(0xD125, &[(0x1, 0x02), (0x2, 0x01)]);
+        chip8.memory[0x202..0x204].copy_from_slice(&[0xD1, 0x25]);
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.v[0xF], 0);
+
+        let ascii = chip8.screen().to_ascii();
+        let rows: Vec<&str> = ascii.lines().collect();
+        assert_eq!(&rows[1][..8], "..####..");
+        assert_eq!(&rows[2][..8], "..#..#..");
+
+        let xrgb = chip8.screen().to_xrgb8888();
+        let [r, g, b] = chip8.screen().palette().color(1);
+        assert_eq!(xrgb[64 + 2], u32::from_be_bytes([0, r, g, b]));
+        assert_eq!(xrgb[64], xrgb[0]);
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.v[0xF], 1);
+        assert!(!chip8.screen().to_ascii().contains('#'));
+
+        // 00E0
+        let mut chip8 = chip8_with(0xD125, &[]);
+        chip8.memory[0x202..0x204].copy_from_slice(&[0x00, 0xE0]);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert!(!chip8.screen().to_ascii().contains('#'));
+    }
+
+    #[test]
+    fn quirk_instructions() {
+        let profile = |quirks: Quirks, opcode: u16, before: RegisterValues| {
+            let mut chip8 = chip8_with(opcode, before);
+            chip8.set_quirks(quirks);
+            chip8.step().unwrap();
+            chip8
+        };
+        let cosmac = quirks::QuirkProfile::Cosmac.quirks();
+        let schip = quirks::QuirkProfile::Schip.quirks();
+
+        // VF reset
+        let chip8 = profile(cosmac, 0x8121, &[(0xF, 0x55)]);
+        assert_eq!(chip8.registers.v[0xF], 0);
+
+        // Shift of Vy
+        let chip8 = profile(cosmac, 0x8126, &[(0x1, 0x10), (0x2, 0x04)]);
+        assert_registers(&chip8, 0x8126, &[(0x1, 0x02), (0xF, 0)]);
+
+        // Load/store increment
+        let chip8 = profile(cosmac, 0xF255, &[]);
+        assert_eq!(chip8.registers.i, 3);
+
+        // Bxnn
+        let chip8 = profile(schip, 0xB300, &[(0x0, 0x04), (0x3, 0x08)]);
+        assert_eq!(chip8.registers.pc, 0x308);
+    }
+
+    #[test]
+    fn errors() {
+        for opcode in [0x5121, 0x8128, 0x9121, 0xE1FF, 0xF1FF, 0x0123].iter() {
+            let mut chip8 = chip8_with(*opcode, &[]);
+            match chip8.step() {
+                Err(Chip8Error::IllegalOpcode(illegal)) => assert_eq!(illegal, *opcode),
+                result => panic!("{:04X} gave {:?}", opcode, result),
+            }
+        }
+
+        let mut chip8 = chip8_with(0x00EE, &[]);
+        assert!(matches!(chip8.step(), Err(Chip8Error::StackUnderflow)));
+
+        // Calls itself forever
+        let mut chip8 = chip8_with(0x2200, &[]);
+        for _ in 0..config::CHIP8_STACK_DEPTH {
+            chip8.step().unwrap();
+        }
+        assert!(matches!(chip8.step(), Err(Chip8Error::StackOverflow)));
+    }
+
+    #[test]
+    fn memory_wraps_around() {
+        // Fx55 and Fx65 at the end of memory
+        let mut chip8 = chip8_with(0xF155, &[(0x0, 0x11), (0x1, 0x22)]);
+        chip8.registers.i = 0xFFF;
+        chip8.step().unwrap();
+        assert_eq!(chip8.memory[0xFFF], 0x11);
+        assert_eq!(chip8.memory[0x000], 0x22);
+
+        // Dxyn with a sprite crossing the end of memory
+        let mut chip8 = chip8_with(0xD00F, &[]);
+        chip8.registers.i = 0xFFA;
+        chip8.step().unwrap();
+
+        // Fetching the last byte of memory
+        let mut chip8 = chip8_with(0x1FFF, &[]);
+        chip8.step().unwrap();
+        chip8.step().unwrap_err();
+    }
+
+    #[test]
+    fn self_modifying_code() {
+        // 6A01 is replaced by 6A02 with Fx55 once it has run, then again by Fx33
+        let program = [0x6A, 0x01, 0xF1, 0x55, 0x12, 0x00];
+        let mut chip8 = chip8_with(0, &[(0x0, 0x6A), (0x1, 0x02)]);
+        chip8.load(&program).unwrap();
+        chip8.registers.i = 0x200;
+        for _ in 0..4 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(chip8.registers.v[0xA], 0x02);
+
+        // Fx33 of 0x6A writes 1, 0, 6 over the first instruction, which becomes illegal
+        chip8.memory[0x202..0x204].copy_from_slice(&[0xF0, 0x33]);
+        chip8.flush_instruction_cache();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.memory[0x200..0x203], [1, 0, 6]);
+        assert!(matches!(
+            chip8.step(),
+            Err(Chip8Error::IllegalOpcode(0x0100))
+        ));
+
+        // Without the cache, direct writes to memory are seen at once
+        let mut chip8 = chip8_with(0x6A01, &[]);
+        chip8.memory[0x202..0x204].copy_from_slice(&[0x12, 0x00]);
+        chip8.set_instruction_cache(false);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        chip8.memory[0x201] = 0x03;
+        chip8.step().unwrap();
+        assert_eq!(chip8.registers.v[0xA], 0x03);
+    }
+
+    #[test]
+    fn block_engines() {
+        // 6B01 in the middle of the first block is replaced by 6B02 with Fx55
+        let program = [0x6A, 0x01, 0x6B, 0x01, 0xF1, 0x55, 0x12, 0x00];
+        for engine in [engine::Engine::Blocks, engine::Engine::Lockstep].iter() {
+            let mut chip8 = chip8_with(0, &[(0x0, 0x6B), (0x1, 0x02)]);
+            chip8.load(&program).unwrap();
+            chip8.set_engine(*engine);
+            chip8.registers.i = 0x202;
+
+            chip8.run_instructions(4).unwrap();
+            assert_eq!(chip8.registers.v[0xB], 0x01);
+            chip8.run_instructions(2).unwrap();
+            assert_eq!(chip8.registers.v[0xB], 0x02);
+            assert_eq!(chip8.registers.pc, 0x204);
+
+            // Execution stops at Fx0A until a key is pressed
+            chip8.memory[0x204..0x206].copy_from_slice(&[0xF5, 0x0A]);
+            chip8.flush_instruction_cache();
+            chip8.run_instructions(10).unwrap();
+            assert_eq!(chip8.registers.pc, 0x206);
+            chip8.key_down(0x9);
+            chip8.run_instructions(1).unwrap();
+            assert_eq!(chip8.registers.v[0x5], 0x9);
+            assert_eq!(chip8.registers.pc, 0x200);
+
+            chip8.memory[0x200..0x202].copy_from_slice(&[0x00, 0xEE]);
+            chip8.flush_instruction_cache();
+            assert!(matches!(
+                chip8.run_instructions(1),
+                Err(Chip8Error::StackUnderflow)
+            ));
+            // Without the cache, direct writes to memory are seen at once
+            let mut chip8 = chip8_with(0x6A01, &[]);
+            chip8.memory[0x202..0x204].copy_from_slice(&[0x12, 0x00]);
+            chip8.set_engine(*engine);
+            chip8.set_instruction_cache(false);
+            chip8.run_instructions(2).unwrap();
+            chip8.memory[0x201] = 0x03;
+            chip8.run_instructions(1).unwrap();
+            assert_eq!(chip8.registers.v[0xA], 0x03);
+        }
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let program = [
+            0x60, 0x42, 0xA2, 0x00, 0xD0, 0x15, 0xC1, 0xFF, 0x22, 0x0A, 0xF2, 0x0A, 0x12, 0x0C,
+        ];
+        let mut chip8 = Chip8::new();
+        chip8.seed(7);
+        chip8.load(&program).unwrap();
+        chip8.set_quirks(quirks::QuirkProfile::Cosmac.quirks());
+        chip8.key_down(0x3);
+        for _ in 0..6 {
+            chip8.step().unwrap();
+        }
+
+        let state = chip8.save_state();
+        let mut restored = Chip8::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+
+        // Both machines continue identically
+        chip8.key_down(0x5);
+        restored.key_down(0x5);
+        chip8.run_frame().unwrap();
+        restored.run_frame().unwrap();
+        assert_eq!(restored.save_state(), chip8.save_state());
+    }
+
+    #[test]
+    fn invalid_save_states() {
+        let mut chip8 = Chip8::new();
+        let state = chip8.save_state();
+
+        assert!(chip8.load_state(&state[..state.len() - 1]).is_err());
+        assert!(chip8
+            .load_state(&[state.clone(), vec![0]].concat())
+            .is_err());
+
+        let mut bad_magic = state.clone();
+        bad_magic[0] ^= 0xFF;
+        assert!(chip8.load_state(&bad_magic).is_err());
+
+        let mut bad_pixel = state.clone();
+        *bad_pixel.last_mut().unwrap() = 2;
+        assert!(chip8.load_state(&bad_pixel).is_err());
+
+        assert_eq!(chip8.save_state(), state);
+    }
+
+    #[test]
+    fn trace_lines() {
+        let chip8 = chip8_with(0x6A2B, &[(0xF, 0x01)]);
+        assert_eq!(
+            chip8.trace_line(),
+            "PC=0200 OP=6A2B V0=00 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00 V8=00 V9=00 \
+             VA=00 VB=00 VC=00 VD=00 VE=00 VF=01 I=0000 SP=00 DT=00 ST=00"
+        );
+
+        // One line per executed instruction, none while waiting for a key
+        let mut chip8 = chip8_with(0xF00A, &[]);
+        let mut lines = Vec::new();
+        chip8
+            .run_frame_traced(&mut |chip8| {
+                lines.push(chip8.trace_line());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("PC=0200 OP=F00A "));
     }
 }
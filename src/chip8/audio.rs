@@ -1,23 +1,167 @@
-use sdl2::audio::AudioCallback;
+use std::f32::consts::PI;
 
-pub struct SquareWave {
-    pub phase_inc: f32,
-    pub phase: f32,
-    pub volume: f32,
+use crate::config;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+    // Duty is the fraction of the period spent high
+    Square { duty: f32 },
+    Triangle,
+    Sine,
+    Noise,
+}
+
+impl Waveform {
+    // Parses "square", "square:<duty>", "triangle", "sine" or "noise"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.splitn(2, ':');
+        let name = parts.next().unwrap_or_default();
+
+        match (name, parts.next()) {
+            ("square", None) => Ok(Waveform::Square { duty: 0.5 }),
+            ("square", Some(duty)) => match duty.parse::<f32>() {
+                Ok(duty) if duty > 0.0 && duty < 1.0 => Ok(Waveform::Square { duty }),
+                _ => Err(format!("'{}' is not a duty cycle between 0 and 1", duty)),
+            },
+            ("triangle", None) => Ok(Waveform::Triangle),
+            ("sine", None) => Ok(Waveform::Sine),
+            ("noise", None) => Ok(Waveform::Noise),
+            _ => Err(format!("Unknown waveform '{}'", s)),
+        }
+    }
+}
+
+// Tone generator for the sound timer, with a short attack and release to avoid clicks.
+// Samples are rendered one emulated frame at a time so beeps last exactly as long as the timer
+#[derive(Clone, Debug)]
+pub struct Buzzer {
+    waveform: Waveform,
+    phase: f32,
+    phase_inc: f32,
+    volume: f32,
+    muted: bool,
+
+    // The envelope ramps up to 1 while the gate is open, and back down to 0 when it closes
+    gate: bool,
+    envelope: f32,
+    attack_step: f32,
+    release_step: f32,
+
+    noise_state: u32,
+    noise_value: f32,
+
+    sample_rate: u32,
+    // Fraction of a sample carried over to the next frame when the rate isn't a multiple of 60
+    sample_remainder: f64,
+    samples: Vec<f32>,
 }
 
-impl AudioCallback for SquareWave {
-    type Channel = f32;
+impl Buzzer {
+    pub fn new(waveform: Waveform, frequency: f32, volume: f32, sample_rate: u32) -> Self {
+        let rate = sample_rate as f32;
+
+        Self {
+            waveform,
+            phase: 0.0,
+            phase_inc: frequency / rate,
+            volume,
+            muted: false,
+
+            gate: false,
+            envelope: 0.0,
+            attack_step: 1.0 / (config::CHIP8_SOUND_ATTACK_TIME * rate),
+            release_step: 1.0 / (config::CHIP8_SOUND_RELEASE_TIME * rate),
+
+            noise_state: 0x1234_5678,
+            noise_value: 1.0,
+
+            sample_rate,
+            sample_remainder: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Render one frame of emulated time, the buzzer sounds for the whole frame or not at all
+    pub fn render_frame(&mut self, playing: bool) -> &[f32] {
+        let samples = self.sample_rate as f64 * config::CHIP8_FRAME_FREQ + self.sample_remainder;
+        let sample_count = samples.floor();
+        self.sample_remainder = samples - sample_count;
+
+        self.gate = playing;
 
-    fn callback(&mut self, out: &mut [Self::Channel]) {
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
+        self.samples.clear();
+        for _ in 0..sample_count as usize {
+            let sample = self.next_sample();
+            self.samples.push(sample);
+        }
+
+        &self.samples[..]
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
 
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+    fn next_sample(&mut self) -> f32 {
+        if self.gate {
+            self.envelope = (self.envelope + self.attack_step).min(1.0);
+        } else {
+            self.envelope = (self.envelope - self.release_step).max(0.0);
         }
+
+        // Restart the waveform from the beginning on every beep
+        if self.envelope == 0.0 {
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let sample = match self.waveform {
+            Waveform::Square { duty } => {
+                if self.phase < duty {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Sine => (self.phase * 2.0 * PI).sin(),
+            Waveform::Noise => self.noise_value,
+        };
+
+        let previous_phase = self.phase;
+        self.phase = (self.phase + self.phase_inc) % 1.0;
+
+        // The noise changes value twice per period so the note frequency still sets its pitch
+        if self.phase < previous_phase || (previous_phase < 0.5 && self.phase >= 0.5) {
+            self.next_noise_value();
+        }
+
+        let volume = if self.muted { 0.0 } else { self.volume };
+        sample * self.envelope * volume
+    }
+
+    fn next_noise_value(&mut self) {
+        // xorshift32
+        self.noise_state ^= self.noise_state << 13;
+        self.noise_state ^= self.noise_state >> 17;
+        self.noise_state ^= self.noise_state << 5;
+
+        self.noise_value = self.noise_state as f32 / u32::MAX as f32 * 2.0 - 1.0;
     }
 }
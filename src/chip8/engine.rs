@@ -0,0 +1,101 @@
+use super::error::Chip8Error;
+use super::Chip8;
+use crate::config;
+
+// How instructions are executed. All engines give the same results, only faster
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Engine {
+    // Fetches and executes one instruction at a time
+    Interpreter,
+    // Runs basic blocks decoded ahead of time, for fast headless and batch runs
+    Blocks,
+    // Runs blocks and checks the machine against the interpreter after each one. Slow, fails
+    // with EngineDivergence at the first difference
+    Lockstep,
+}
+
+impl Engine {
+    pub const ALL: [Engine; 3] = [Engine::Interpreter, Engine::Blocks, Engine::Lockstep];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Interpreter => "interpreter",
+            Engine::Blocks => "blocks",
+            Engine::Lockstep => "lockstep",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|engine| engine.name() == name)
+    }
+}
+
+impl Chip8 {
+    pub(super) fn run_blocks(&mut self, count: usize) -> Result<(), Chip8Error> {
+        let mut executed = 0;
+        while executed < count && self.waiting_for_key.is_none() {
+            self.run_block(count - executed, &mut executed)?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn run_lockstep(&mut self, count: usize) -> Result<(), Chip8Error> {
+        let mut reference = Chip8::new();
+        reference.load_state(&self.save_state())?;
+
+        let mut remaining = count;
+        while remaining > 0 && self.waiting_for_key.is_none() {
+            let addr = self.registers.pc;
+            let mut executed = 0;
+            let result = self.run_block(remaining, &mut executed);
+            let reference_result = (0..executed).try_for_each(|_| reference.step());
+
+            if result.is_ok() != reference_result.is_ok()
+                || reference.save_state() != self.save_state()
+            {
+                return Err(Chip8Error::EngineDivergence(addr));
+            }
+
+            result?;
+            remaining -= executed;
+        }
+
+        Ok(())
+    }
+
+    // Runs at most `budget` instructions from the block at PC, for as long as PC stays in it.
+    // Counts them in `executed`, including one that failed
+    fn run_block(&mut self, budget: usize, executed: &mut usize) -> Result<(), Chip8Error> {
+        let start = self.registers.pc as usize;
+        // Instructions wrapping around the end of memory are left to the interpreter, and so is
+        // everything when `memory` may be written directly
+        if start >= config::CHIP8_MEMORY_SIZE - 1 || !self.instruction_cache {
+            *executed += 1;
+            return self.step();
+        }
+
+        let block = self.blocks.get(&self.memory, start);
+        for _ in 0..budget {
+            let offset = (self.registers.pc as usize).wrapping_sub(start);
+            let instruction = match block.get(offset / 2) {
+                Some(instruction) if offset.is_multiple_of(2) => *instruction,
+                _ => break,
+            };
+
+            *executed += 1;
+            self.registers.pc = self.registers.pc.wrapping_add(2);
+            self.execute(instruction)?;
+
+            // The block may be stale after a store
+            if instruction.writes_memory() || self.waiting_for_key.is_some() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,27 +1,66 @@
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter, Result};
+use std::io;
+
 pub enum Chip8Error {
     ProgramTooLarge,
+    IllegalOpcode(u16),
+    StackOverflow,
+    StackUnderflow,
+    InvalidState,
+    EngineDivergence(u16),
+    Io(io::Error),
 }
 
 impl Chip8Error {
     fn message(&self) -> &str {
         match self {
             Chip8Error::ProgramTooLarge => "The program size is too large",
+            Chip8Error::IllegalOpcode(_) => "Illegal opcode",
+            Chip8Error::StackOverflow => "Too many nested subroutine calls",
+            Chip8Error::StackUnderflow => "Return outside of a subroutine",
+            Chip8Error::InvalidState => "The save state is invalid",
+            Chip8Error::EngineDivergence(_) => {
+                "The block engine diverged from the interpreter in the block at"
+            }
+            Chip8Error::Io(_) => "I/O error",
         }
     }
 }
 
-impl Error for Chip8Error {}
+impl Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Chip8Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl Display for Chip8Error {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "{}", self.message())
+        if let Chip8Error::IllegalOpcode(opcode) = self {
+            return write!(f, "{} {:04X}", self.message(), opcode);
+        }
+        if let Chip8Error::EngineDivergence(addr) = self {
+            return write!(f, "{} {:03X}", self.message(), addr);
+        }
+
+        match self.source() {
+            Some(source) => write!(f, "{}: {}", self.message(), source),
+            None => write!(f, "{}", self.message()),
+        }
     }
 }
 
 impl Debug for Chip8Error {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        write!(f, "{}", self.message())
+        Display::fmt(self, f)
+    }
+}
+
+impl From<io::Error> for Chip8Error {
+    fn from(err: io::Error) -> Self {
+        Chip8Error::Io(err)
     }
 }
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+// An opcode with its operands extracted, so a cached instruction executes without decoding it
+// again. Registers are indexes in V and n is the sprite height
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    Cls,
+    Ret,
+    Jp(u16),
+    Call(u16),
+    SeVxByte(u8, u8),
+    SneVxByte(u8, u8),
+    SeVxVy(u8, u8),
+    LdVxByte(u8, u8),
+    AddVxByte(u8, u8),
+    LdVxVy(u8, u8),
+    OrVxVy(u8, u8),
+    AndVxVy(u8, u8),
+    XorVxVy(u8, u8),
+    AddVxVy(u8, u8),
+    SubVxVy(u8, u8),
+    ShrVx(u8, u8),
+    SubnVxVy(u8, u8),
+    ShlVx(u8, u8),
+    SneVxVy(u8, u8),
+    LdI(u16),
+    JpV0(u16),
+    Rnd(u8, u8),
+    Drw(u8, u8, u8),
+    Skp(u8),
+    Sknp(u8),
+    LdVxDt(u8),
+    LdVxK(u8),
+    LdDtVx(u8),
+    LdStVx(u8),
+    AddIVx(u8),
+    LdFVx(u8),
+    LdBVx(u8),
+    LdIVx(u8),
+    LdVxI(u8),
+    // Fails with IllegalOpcode when executed
+    Illegal(u16),
+}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Self {
+        let nnn = opcode & 0x0FFF;
+        let n = (opcode & 0x000F) as u8;
+        let x = ((opcode >> 8) & 0x000F) as u8;
+        let y = ((opcode >> 4) & 0x000F) as u8;
+        let kk = (opcode & 0x00FF) as u8;
+
+        match opcode & 0xF000 {
+            0x0000 => match opcode {
+                0x00E0 => Self::Cls,
+                0x00EE => Self::Ret,
+                _ => Self::Illegal(opcode),
+            },
+            0x1000 => Self::Jp(nnn),
+            0x2000 => Self::Call(nnn),
+            0x3000 => Self::SeVxByte(x, kk),
+            0x4000 => Self::SneVxByte(x, kk),
+            0x5000 if n == 0x0 => Self::SeVxVy(x, y),
+            0x6000 => Self::LdVxByte(x, kk),
+            0x7000 => Self::AddVxByte(x, kk),
+            0x8000 => match n {
+                0x0 => Self::LdVxVy(x, y),
+                0x1 => Self::OrVxVy(x, y),
+                0x2 => Self::AndVxVy(x, y),
+                0x3 => Self::XorVxVy(x, y),
+                0x4 => Self::AddVxVy(x, y),
+                0x5 => Self::SubVxVy(x, y),
+                0x6 => Self::ShrVx(x, y),
+                0x7 => Self::SubnVxVy(x, y),
+                0xE => Self::ShlVx(x, y),
+                _ => Self::Illegal(opcode),
+            },
+            0x9000 if n == 0x0 => Self::SneVxVy(x, y),
+            0xA000 => Self::LdI(nnn),
+            0xB000 => Self::JpV0(nnn),
+            0xC000 => Self::Rnd(x, kk),
+            0xD000 => Self::Drw(x, y, n),
+            0xE000 => match kk {
+                0x9E => Self::Skp(x),
+                0xA1 => Self::Sknp(x),
+                _ => Self::Illegal(opcode),
+            },
+            0xF000 => match kk {
+                0x07 => Self::LdVxDt(x),
+                0x0A => Self::LdVxK(x),
+                0x15 => Self::LdDtVx(x),
+                0x18 => Self::LdStVx(x),
+                0x1E => Self::AddIVx(x),
+                0x29 => Self::LdFVx(x),
+                0x33 => Self::LdBVx(x),
+                0x55 => Self::LdIVx(x),
+                0x65 => Self::LdVxI(x),
+                _ => Self::Illegal(opcode),
+            },
+            _ => Self::Illegal(opcode),
+        }
+    }
+}
+
+// Instructions decoded at each address of memory. An instruction starting at any address, even
+// or odd, is decoded on its first execution and cached until one of its two bytes is written
+pub struct InstructionCache {
+    instructions: Box<[Option<Instruction>]>,
+}
+
+impl InstructionCache {
+    pub fn new(size: usize) -> Self {
+        Self {
+            instructions: vec![None; size].into_boxed_slice(),
+        }
+    }
+
+    pub fn get(&self, addr: usize) -> Option<Instruction> {
+        self.instructions[addr]
+    }
+
+    pub fn insert(&mut self, addr: usize, instruction: Instruction) {
+        self.instructions[addr] = Some(instruction);
+    }
+
+    // A write to `addr` changes the instruction starting there and the one starting just before
+    pub fn invalidate(&mut self, addr: usize) {
+        let size = self.instructions.len();
+        self.instructions[addr % size] = None;
+        self.instructions[(addr + size - 1) % size] = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.instructions
+            .iter_mut()
+            .for_each(|instruction| *instruction = None);
+    }
+}
+
+// Longest run of instructions decoded into a block
+const MAX_BLOCK_LENGTH: usize = 32;
+
+impl Instruction {
+    // Whether execution never continues at the next instruction, which ends a block
+    fn ends_block(self) -> bool {
+        use Instruction::*;
+
+        matches!(self, Ret | Jp(_) | Call(_) | JpV0(_) | Illegal(_))
+    }
+
+    // Stores may overwrite the running block
+    pub fn writes_memory(self) -> bool {
+        matches!(self, Instruction::LdBVx(_) | Instruction::LdIVx(_))
+    }
+}
+
+// Basic blocks by start address: runs of decoded instructions up to a jump, executed without
+// fetching each one. Skips and jumps landing inside a block continue in it. A block is dropped
+// when any of its bytes is written
+pub struct BlockCache {
+    blocks: Box<[Option<Arc<[Instruction]>>]>,
+}
+
+impl BlockCache {
+    pub fn new(size: usize) -> Self {
+        Self {
+            blocks: vec![None; size].into_boxed_slice(),
+        }
+    }
+
+    // The block starting at `addr`, decoded from `memory` the first time. Blocks stop at the
+    // end of memory, `addr` must leave room for at least one instruction
+    pub fn get(&mut self, memory: &[u8], addr: usize) -> Arc<[Instruction]> {
+        if let Some(block) = &self.blocks[addr] {
+            return block.clone();
+        }
+
+        let mut instructions = Vec::new();
+        for pc in (addr..memory.len() - 1).step_by(2).take(MAX_BLOCK_LENGTH) {
+            let instruction = Instruction::decode(u16::from_be_bytes([memory[pc], memory[pc + 1]]));
+            instructions.push(instruction);
+            if instruction.ends_block() {
+                break;
+            }
+        }
+
+        let block: Arc<[Instruction]> = instructions.into();
+        self.blocks[addr] = Some(block.clone());
+        block
+    }
+
+    pub fn invalidate(&mut self, addr: usize) {
+        let first = (addr + 1).saturating_sub(MAX_BLOCK_LENGTH * 2);
+        for start in first..=addr {
+            let covers_addr = match &self.blocks[start] {
+                Some(block) => start + block.len() * 2 > addr,
+                None => false,
+            };
+            if covers_addr {
+                self.blocks[start] = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.iter_mut().for_each(|block| *block = None);
+    }
+}
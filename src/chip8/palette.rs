@@ -0,0 +1,121 @@
+pub type Rgb = [u8; 3];
+
+// Colors of the display, indexed by the planes a pixel is lit on
+// (0: background, 1: first plane, 2: second plane, 3: both planes). The display only has the
+// first plane, colors 2 and 3 are reserved for an XO-CHIP plane mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+impl Palette {
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn with_background(mut self, color: Rgb) -> Self {
+        self.colors[0] = color;
+        self
+    }
+
+    pub fn with_foreground(mut self, color: Rgb) -> Self {
+        self.colors[1] = color;
+        self
+    }
+
+    pub fn color(&self, planes: usize) -> Rgb {
+        self.colors[planes]
+    }
+
+    // Parses a color written as "RRGGBB" or "#RRGGBB"
+    pub fn parse_color(s: &str) -> Option<Rgb> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 {
+            return None;
+        }
+
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        Some([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Theme::Classic.palette()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Theme {
+    Classic,
+    Amber,
+    GreenPhosphor,
+    Lcd,
+    Octo,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 5] = [
+        Theme::Classic,
+        Theme::Amber,
+        Theme::GreenPhosphor,
+        Theme::Lcd,
+        Theme::Octo,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::Amber => "amber",
+            Theme::GreenPhosphor => "green",
+            Theme::Lcd => "lcd",
+            Theme::Octo => "octo",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|theme| theme.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|theme| *theme == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn palette(self) -> Palette {
+        let colors = match self {
+            Theme::Classic => [
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0xAA, 0xAA, 0xAA],
+                [0x55, 0x55, 0x55],
+            ],
+            Theme::Amber => [
+                [0x1A, 0x0F, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0xCC, 0x66, 0x00],
+                [0xFF, 0xE0, 0x80],
+            ],
+            Theme::GreenPhosphor => [
+                [0x00, 0x14, 0x00],
+                [0x33, 0xFF, 0x33],
+                [0x00, 0x99, 0x33],
+                [0xB0, 0xFF, 0xB0],
+            ],
+            Theme::Lcd => [
+                [0x9B, 0xBC, 0x0F],
+                [0x0F, 0x38, 0x0F],
+                [0x30, 0x62, 0x30],
+                [0x5A, 0x7E, 0x1A],
+            ],
+            Theme::Octo => [
+                [0x99, 0x66, 0x00],
+                [0xFF, 0xCC, 0x00],
+                [0xFF, 0x66, 0x00],
+                [0x66, 0x22, 0x00],
+            ],
+        };
+
+        Palette { colors }
+    }
+}
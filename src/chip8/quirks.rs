@@ -0,0 +1,79 @@
+// Behaviours that differ between CHIP-8 interpreters, programs written for one may rely on them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+    // 8xy1, 8xy2 and 8xy3 reset VF to 0
+    pub vf_reset: bool,
+    // 8xy6 and 8xyE shift Vy into Vx instead of shifting Vx in place
+    pub shift_vy: bool,
+    // Fx55 and Fx65 leave I pointing after the last register
+    pub load_store_increment: bool,
+    // Bnnn is Bxnn and jumps to xnn + Vx instead of nnn + V0
+    pub jump_vx: bool,
+    // Sprites are clipped at the edges of the display instead of wrapping around
+    pub clipping: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        QuirkProfile::Modern.quirks()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuirkProfile {
+    // The behaviour of most modern interpreters
+    Modern,
+    // The original COSMAC VIP interpreter
+    Cosmac,
+    // SUPER-CHIP on the HP 48
+    Schip,
+}
+
+impl QuirkProfile {
+    pub const ALL: [QuirkProfile; 3] = [
+        QuirkProfile::Modern,
+        QuirkProfile::Cosmac,
+        QuirkProfile::Schip,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuirkProfile::Modern => "modern",
+            QuirkProfile::Cosmac => "cosmac",
+            QuirkProfile::Schip => "schip",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|profile| profile.name() == name)
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            QuirkProfile::Modern => Quirks {
+                vf_reset: false,
+                shift_vy: false,
+                load_store_increment: false,
+                jump_vx: false,
+                clipping: false,
+            },
+            QuirkProfile::Cosmac => Quirks {
+                vf_reset: true,
+                shift_vy: true,
+                load_store_increment: true,
+                jump_vx: false,
+                clipping: true,
+            },
+            QuirkProfile::Schip => Quirks {
+                vf_reset: false,
+                shift_vy: false,
+                load_store_increment: false,
+                jump_vx: true,
+                clipping: true,
+            },
+        }
+    }
+}
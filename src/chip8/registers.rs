@@ -22,3 +22,9 @@ impl Registers {
         }
     }
 }
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,39 @@
+// Deterministic xorshift64* generator for RND, so a run can be reproduced from its seed
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed with splitmix64 so close seeds give unrelated sequences
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        // xorshift gets stuck on 0
+        Self { state: z.max(1) }
+    }
+
+    // Resume a sequence from a saved state, None for the invalid state 0
+    pub fn from_state(state: u64) -> Option<Self> {
+        if state == 0 {
+            return None;
+        }
+
+        Some(Self { state })
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+}
@@ -1,78 +1,297 @@
+use super::palette::Palette;
 use crate::config;
 
+const WIDTH: usize = config::CHIP8_WIDTH as usize;
+const HEIGHT: usize = config::CHIP8_HEIGHT as usize;
+const PIXEL_COUNT: usize = WIDTH * HEIGHT;
+
+// Bit of the leftmost pixel in a row
+const LEFT_PIXEL: u64 = 1 << 63;
+
 #[derive(Debug)]
 pub struct Screen {
-    pixel_states: [bool; config::CHIP8_WIDTH as usize * config::CHIP8_HEIGHT as usize],
-    pixels_for_draw: [u8; config::CHIP8_WIDTH as usize * config::CHIP8_HEIGHT as usize * 3],
+    // One bit per pixel, one word per row with the leftmost pixel in the highest bit. Colors
+    // are only produced when the display is read, with the current palette
+    rows: [u64; HEIGHT],
+    palette: Palette,
+
+    // Phosphor persistence: lit pixels fade out over several frames instead of turning off at once
+    persistence: bool,
+    intensities: [f32; PIXEL_COUNT],
+
+    // Counts the changes of what the display shows, frontends skip redrawing while it stays
+    revision: u64,
 }
 
 impl Screen {
     pub fn new() -> Self {
         Self {
-            pixel_states: [false; config::CHIP8_WIDTH as usize * config::CHIP8_HEIGHT as usize],
-            pixels_for_draw: [0; config::CHIP8_WIDTH as usize * config::CHIP8_HEIGHT as usize * 3],
+            rows: [0; HEIGHT],
+            palette: Palette::default(),
+            persistence: false,
+            intensities: [0.0; PIXEL_COUNT],
+            revision: 0,
         }
     }
 
-    pub fn pixel_colors(&self) -> &[u8] {
-        &self.pixels_for_draw[..]
+    // RGB bytes of every pixel, row by row
+    pub fn pixel_colors(&self) -> Vec<u8> {
+        let mut colors = vec![0; PIXEL_COUNT * 3];
+        let palette = [self.palette.color(0), self.palette.color(1)];
+
+        for (index, color) in colors.chunks_exact_mut(3).enumerate() {
+            if self.persistence {
+                color.copy_from_slice(&self.pixel_color(index));
+            } else {
+                let lit = is_lit(&self.rows, index);
+                color.copy_from_slice(&palette[lit as usize]);
+            }
+        }
+
+        colors
     }
 
-    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
-        let mut pixel_erased = false;
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
 
-        for (ly, c) in sprite.iter().enumerate() {
-            for lx in 0..8 {
-                if *c & (0x80 >> lx) == 0 {
-                    continue;
-                }
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.revision += 1;
+    }
+
+    pub fn persistence(&self) -> bool {
+        self.persistence
+    }
+
+    pub fn set_persistence(&mut self, enabled: bool) {
+        self.persistence = enabled;
+
+        for (index, intensity) in self.intensities.iter_mut().enumerate() {
+            *intensity = if is_lit(&self.rows, index) { 1.0 } else { 0.0 };
+        }
+        self.revision += 1;
+    }
+
+    // Changes whenever the pixels or their colors may have changed: after drawing, clearing,
+    // fading or a palette change. Equal revisions show the same display
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // The pixels of each row as bits, the leftmost pixel in the highest bit
+    pub fn rows(&self) -> &[u64] {
+        &self.rows[..]
+    }
+
+    // Whether each pixel is lit, row by row
+    pub fn pixel_states(&self) -> Vec<bool> {
+        let mut states = Vec::with_capacity(PIXEL_COUNT);
+        for row in self.rows.iter() {
+            states.extend((0..WIDTH).map(|x| row & (LEFT_PIXEL >> x) != 0));
+        }
+
+        states
+    }
+
+    // Replace the whole display, e.g. when loading a save state
+    pub fn set_pixel_states(&mut self, states: &[bool]) {
+        for (row, states) in self.rows.iter_mut().zip(states.chunks_exact(WIDTH)) {
+            *row = states.iter().fold(0, |row, lit| row << 1 | *lit as u64);
+        }
+        self.set_persistence(self.persistence);
+    }
+
+    // Fade out the pixels that are off, must be called once per frame
+    pub fn fade(&mut self) {
+        if !self.persistence {
+            return;
+        }
+
+        let mut faded = false;
+        for (index, intensity) in self.intensities.iter_mut().enumerate() {
+            let faded_intensity = if is_lit(&self.rows, index) {
+                1.0
+            } else if *intensity > config::CHIP8_PERSISTENCE_MIN_INTENSITY {
+                *intensity * config::CHIP8_PERSISTENCE_DECAY
+            } else {
+                0.0
+            };
+
+            faded |= faded_intensity != *intensity;
+            *intensity = faded_intensity;
+        }
+
+        if faded {
+            self.revision += 1;
+        }
+    }
+
+    // FNV-1a hash of the pixel states, stable across runs and platforms
+    pub fn hash(&self) -> u64 {
+        let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+        for row in self.rows.iter() {
+            for x in 0..WIDTH {
+                hash ^= row >> (WIDTH - 1 - x) & 1;
+                hash = hash.wrapping_mul(0x0100_0000_01B3);
+            }
+        }
+
+        hash
+    }
 
-                let index_x = (lx + x) % config::CHIP8_WIDTH as usize;
-                let index_y = (ly + y) % config::CHIP8_HEIGHT as usize;
+    // Pixel colors as 0x00RRGGBB words, the format of most frontend APIs
+    pub fn to_xrgb8888(&self) -> Vec<u32> {
+        (0..PIXEL_COUNT)
+            .map(|index| {
+                let [r, g, b] = self.pixel_color(index);
+                (r as u32) << 16 | (g as u32) << 8 | b as u32
+            })
+            .collect()
+    }
 
-                if self.pixel(index_x, index_y) {
-                    pixel_erased = true;
+    // One line per row, '#' for lit pixels and '.' for unlit ones
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity(PIXEL_COUNT + HEIGHT);
+        for row in self.rows.iter() {
+            ascii.extend((0..WIDTH).map(|x| {
+                if row & (LEFT_PIXEL >> x) != 0 {
+                    '#'
+                } else {
+                    '.'
                 }
+            }));
+            ascii.push('\n');
+        }
+
+        ascii
+    }
+
+    // The sprite starts at (x, y) wrapped around the display, then its pixels past the edges
+    // either wrap around or are clipped. Each sprite row is XORed into a display row at once
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8], clipping: bool) -> bool {
+        let (x, y) = (x % WIDTH, y % HEIGHT);
+        let mut pixel_erased = false;
+        let mut sprite_drawn = false;
+
+        for (ly, byte) in sprite.iter().enumerate() {
+            if clipping && y + ly >= HEIGHT {
+                break;
+            }
+
+            let bits = (*byte as u64) << (WIDTH - 8);
+            let bits = if clipping {
+                bits >> x
+            } else {
+                bits.rotate_right(x as u32)
+            };
+
+            if bits == 0 {
+                continue;
+            }
+
+            let row_y = (y + ly) % HEIGHT;
+            let row = self.rows[row_y];
+            pixel_erased |= row & bits != 0;
+            self.rows[row_y] = row ^ bits;
+            sprite_drawn = true;
 
-                self.set_pixel(index_x, index_y, true);
+            // Erased pixels keep their intensity until the next fade, lit ones are at full
+            // intensity
+            if self.persistence {
+                self.light_up(row_y, bits & !row);
             }
         }
 
+        // A sprite always changes the display, unless all its pixels are off or clipped
+        if sprite_drawn {
+            self.revision += 1;
+        }
+
         pixel_erased
     }
 
     pub fn clear(&mut self) {
-        for val in self.pixel_states.iter_mut() {
-            *val = false;
+        if self.rows.iter().any(|row| *row != 0) {
+            // Erased pixels fade out on the next frames
+            self.rows = [0; HEIGHT];
+            self.revision += 1;
         }
+    }
 
-        for val in self.pixels_for_draw.iter_mut() {
-            *val = 0x00;
+    fn light_up(&mut self, y: usize, mut bits: u64) {
+        while bits != 0 {
+            let x = bits.leading_zeros() as usize;
+            self.intensities[y * WIDTH + x] = 1.0;
+            bits &= !(LEFT_PIXEL >> x);
         }
     }
 
-    fn pixel(&self, x: usize, y: usize) -> bool {
-        self.pixel_states[Self::convert_2d_to_1d(x, y)]
-    }
+    fn pixel_color(&self, index: usize) -> [u8; 3] {
+        let lit = is_lit(&self.rows, index);
+        if !self.persistence || lit {
+            return self.palette.color(lit as usize);
+        }
 
-    fn set_pixel(&mut self, x: usize, y: usize, val: bool) {
+        let intensity = self.intensities[index];
+        let background = self.palette.background();
+        let foreground = self.palette.color(1);
+
+        let mut color = [0; 3];
         for i in 0..3 {
-            let index = Screen::convert_2d_to_1d_for_draw(x, y);
-            if val {
-                self.pixels_for_draw[index + i] ^= 0xFF;
-            } else {
-                self.pixels_for_draw[index + i] ^= 0x00;
-            }
+            let from = background[i] as f32;
+            let to = foreground[i] as f32;
+            color[i] = (from + (to - from) * intensity).round() as u8;
         }
-
-        self.pixel_states[Self::convert_2d_to_1d(x, y)] ^= val;
+        color
     }
+}
 
-    fn convert_2d_to_1d(x: usize, y: usize) -> usize {
-        y * config::CHIP8_WIDTH as usize + x
+// Whether the pixel at `index`, counted row by row, is lit
+fn is_lit(rows: &[u64; HEIGHT], index: usize) -> bool {
+    rows[index / WIDTH] & (LEFT_PIXEL >> (index % WIDTH)) != 0
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::palette::Theme;
+
+    #[test]
+    fn screen_revisions() {
+        let mut screen = Screen::new();
+        let revision = screen.revision();
 
-    fn convert_2d_to_1d_for_draw(x: usize, y: usize) -> usize {
-        y * config::CHIP8_WIDTH as usize * 3 + x * 3
+        // Nothing to draw or clear
+        screen.draw_sprite(0, 0, &[0x00, 0x00], false);
+        screen.clear();
+        screen.fade();
+        assert_eq!(screen.revision(), revision);
+
+        screen.draw_sprite(60, 0, &[0x0F], true);
+        assert_eq!(screen.revision(), revision);
+        screen.draw_sprite(60, 0, &[0xFF], true);
+        assert_ne!(screen.revision(), revision);
+
+        let revision = screen.revision();
+        screen.set_palette(Theme::Amber.palette());
+        assert_ne!(screen.revision(), revision);
+
+        // Erased pixels fade out over a few frames, then the display stays the same
+        screen.set_persistence(true);
+        screen.clear();
+        let mut revisions = vec![screen.revision()];
+        for _ in 0..20 {
+            screen.fade();
+            revisions.push(screen.revision());
+        }
+        revisions.dedup();
+        assert!(revisions.len() > 2 && revisions.len() < 20);
     }
 }
@@ -0,0 +1,170 @@
+use super::error::Chip8Error;
+use super::quirks::Quirks;
+use super::rng::Rng;
+use super::Chip8;
+use crate::config;
+
+// Start of every save state, the last byte is the version of the format
+const MAGIC: &[u8; 4] = b"C8S\x01";
+
+// waiting_for_key when no instruction is waiting for a key
+const NOT_WAITING: u8 = 0xFF;
+
+const PIXEL_COUNT: usize = config::CHIP8_WIDTH as usize * config::CHIP8_HEIGHT as usize;
+
+impl Chip8 {
+    // Snapshot of the whole machine, the display settings aside
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(config::CHIP8_MEMORY_SIZE + PIXEL_COUNT + 128);
+        state.extend_from_slice(MAGIC);
+
+        state.extend_from_slice(&self.memory);
+        state.extend_from_slice(&self.registers.v);
+        state.extend_from_slice(&self.registers.i.to_le_bytes());
+        state.push(self.registers.dt);
+        state.push(self.registers.st);
+        state.extend_from_slice(&self.registers.pc.to_le_bytes());
+        state.push(self.registers.sp);
+        for address in self.stack.iter() {
+            state.extend_from_slice(&address.to_le_bytes());
+        }
+
+        state.extend(self.keyboard.key_states().iter().map(|down| *down as u8));
+        state.push(self.waiting_for_key.map_or(NOT_WAITING, |x| x as u8));
+        state.extend_from_slice(&self.cycle_remainder.to_bits().to_le_bytes());
+        state.extend_from_slice(&self.rng.state().to_le_bytes());
+
+        let quirks = self.quirks;
+        state.extend_from_slice(&[
+            quirks.vf_reset as u8,
+            quirks.shift_vy as u8,
+            quirks.load_store_increment as u8,
+            quirks.jump_vx as u8,
+            quirks.clipping as u8,
+        ]);
+
+        state.extend(self.screen.pixel_states().iter().map(|lit| *lit as u8));
+
+        state
+    }
+
+    // Restore a snapshot taken by save_state, the machine is left untouched if it is invalid
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+        let mut reader = StateReader { bytes: state };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Chip8Error::InvalidState);
+        }
+
+        let memory = reader.take(config::CHIP8_MEMORY_SIZE)?;
+        let v = reader.take(config::CHIP8_DATA_REGISTER_COUNT)?;
+        let i = reader.u16()?;
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+        let pc = reader.u16()?;
+        let sp = reader.u8()?;
+        if sp as usize > config::CHIP8_STACK_DEPTH {
+            return Err(Chip8Error::InvalidState);
+        }
+        let mut stack = [0; config::CHIP8_STACK_DEPTH];
+        for address in stack.iter_mut() {
+            *address = reader.u16()?;
+        }
+
+        let mut key_states = [false; config::CHIP8_KEY_COUNT];
+        for down in key_states.iter_mut() {
+            *down = reader.bool()?;
+        }
+        let waiting_for_key = match reader.u8()? {
+            NOT_WAITING => None,
+            x if (x as usize) < config::CHIP8_DATA_REGISTER_COUNT => Some(x as usize),
+            _ => return Err(Chip8Error::InvalidState),
+        };
+        let cycle_remainder = f64::from_bits(reader.u64()?);
+        if !(0.0..1.0).contains(&cycle_remainder) {
+            return Err(Chip8Error::InvalidState);
+        }
+        let rng = Rng::from_state(reader.u64()?).ok_or(Chip8Error::InvalidState)?;
+
+        let quirks = Quirks {
+            vf_reset: reader.bool()?,
+            shift_vy: reader.bool()?,
+            load_store_increment: reader.bool()?,
+            jump_vx: reader.bool()?,
+            clipping: reader.bool()?,
+        };
+
+        let mut pixel_states = [false; PIXEL_COUNT];
+        for lit in pixel_states.iter_mut() {
+            *lit = reader.bool()?;
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(Chip8Error::InvalidState);
+        }
+
+        self.memory.copy_from_slice(memory);
+        self.flush_instruction_cache();
+        self.registers.v.copy_from_slice(v);
+        self.registers.i = i;
+        self.registers.dt = dt;
+        self.registers.st = st;
+        self.registers.pc = pc;
+        self.registers.sp = sp;
+        self.stack = stack;
+        for (key, down) in key_states.iter().enumerate() {
+            if *down {
+                self.keyboard.press(key);
+            } else {
+                self.keyboard.release(key);
+            }
+        }
+        self.waiting_for_key = waiting_for_key;
+        self.cycle_remainder = cycle_remainder;
+        self.rng = rng;
+        self.quirks = quirks;
+        self.screen.set_pixel_states(&pixel_states);
+
+        Ok(())
+    }
+}
+
+struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        if self.bytes.len() < len {
+            return Err(Chip8Error::InvalidState);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Chip8Error> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, Chip8Error> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn bool(&mut self) -> Result<bool, Chip8Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Chip8Error::InvalidState),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::fmt::Write;
+
+use super::Chip8;
+
+impl Chip8 {
+    // State before the next instruction, as "PC=0200 OP=00E0 V0=00 .. VF=00 I=0000 SP=00 DT=00
+    // ST=00". The format is stable so traces can be compared with other emulators
+    pub fn trace_line(&self) -> String {
+        let registers = &self.registers;
+        let mut line = String::with_capacity(112);
+
+        write!(
+            line,
+            "PC={:04X} OP={:04X}",
+            registers.pc,
+            self.get_u16(registers.pc)
+        )
+        .unwrap();
+        for (i, v) in registers.v.iter().enumerate() {
+            write!(line, " V{:X}={:02X}", i, v).unwrap();
+        }
+        write!(
+            line,
+            " I={:04X} SP={:02X} DT={:02X} ST={:02X}",
+            registers.i, registers.sp, registers.dt, registers.st
+        )
+        .unwrap();
+
+        line
+    }
+}
@@ -1,6 +1,3 @@
-use sdl2::keyboard::Keycode;
-use std::collections::HashMap;
-
 pub const WINDOW_TITLE: &str = "Chip8 Window";
 pub const CHIP8_MEMORY_SIZE: usize = 0x1000;
 pub const CHIP8_PROGRAM_LOAD_ADDRESS: usize = 0x200;
@@ -11,31 +8,101 @@ pub const CHIP8_DATA_REGISTER_COUNT: usize = 16;
 pub const CHIP8_STACK_DEPTH: usize = 16;
 pub const CHIP8_KEY_COUNT: usize = 16;
 pub const CHIP8_CHARACTER_SET_SIZE: usize = 80;
-pub const CHIP8_DELAY_TIMER_FREQ: f64 = 1.0 / 60.0;
-pub const CHIP8_SOUND_TIMER_FREQ: f64 = 1.0 / 60.0;
 pub const CHIP8_EXEC_FREQ: f64 = 1.0 / 500.0; // Speed of execution of instructions
 pub const CHIP8_DEFAULT_SPRITE_HEIGHT: u8 = 5;
 pub const CHIP8_SOUND_NOTE_FREQ: f32 = 440.0;
+pub const CHIP8_SOUND_SAMPLE_RATE: i32 = 44100;
+pub const CHIP8_SOUND_VOLUME: f32 = 0.05;
+pub const CHIP8_SOUND_VOLUME_STEP: f32 = 0.01;
+pub const CHIP8_SOUND_MAX_LATENCY: f64 = 0.1; // Seconds of audio queued ahead of the device
+pub const CHIP8_SOUND_ATTACK_TIME: f32 = 0.002; // Seconds for the buzzer to fade in
+pub const CHIP8_SOUND_RELEASE_TIME: f32 = 0.005; // Seconds for the buzzer to fade out
+pub const CHIP8_FRAME_FREQ: f64 = 1.0 / 60.0; // Timers and audio advance once per frame
+pub const CHIP8_MAX_FRAME_LAG: u32 = 4;
+pub const CHIP8_PERSISTENCE_DECAY: f32 = 0.5; // Intensity kept by an unlit pixel after each frame
+pub const CHIP8_PERSISTENCE_MIN_INTENSITY: f32 = 1.0 / 255.0;
+pub const CHIP8_CAPTURE_MAX_SCALE: u32 = 64; // Screenshots and recordings up to 4096x2048
+pub const CHIP8_CRT_SCALE_FACTOR: u32 = 8;
+pub const CHIP8_CRT_SCANLINE_INTENSITY: f32 = 0.6;
+pub const CHIP8_CRT_GRID_INTENSITY: f32 = 0.75;
+pub const CHIP8_CRT_BLOOM_RADIUS: usize = 6;
+pub const CHIP8_CRT_BLOOM_STRENGTH: f32 = 0.5;
+pub const CHIP8_CRT_CURVATURE: f32 = 0.08;
+pub const CHIP8_KEYPAD_WIDTH: u32 = CHIP8_HEIGHT * CHIP8_WINDOW_SCALE_FACTOR;
+pub const CHIP8_TUI_KEY_HOLD_FRAMES: u32 = 45; // Without release events, past the repeat delay
+pub const CHIP8_TUI_KEY_REPEAT_HOLD_FRAMES: u32 = 15; // Frames a key stays down after a repeat
+
+// Hotkeys, keypad colors and key bindings of the SDL frontend
+#[cfg(feature = "sdl")]
+pub use self::sdl::*;
+
+#[cfg(feature = "sdl")]
+mod sdl {
+    use sdl2::keyboard::Keycode;
+    use sdl2::pixels::Color;
+    use std::collections::HashMap;
+
+    pub const HOTKEY_NEXT_THEME: Keycode = Keycode::F2;
+    pub const HOTKEY_TOGGLE_PERSISTENCE: Keycode = Keycode::F3;
+    pub const HOTKEY_TOGGLE_CRT: Keycode = Keycode::F4;
+    pub const HOTKEY_NEXT_SCALE_MODE: Keycode = Keycode::F5;
+    pub const HOTKEY_TOGGLE_RECORDING: Keycode = Keycode::F8;
+    pub const HOTKEY_TOGGLE_MUTE: Keycode = Keycode::F9;
+    pub const HOTKEY_VOLUME_DOWN: Keycode = Keycode::F10;
+    pub const HOTKEY_VOLUME_UP: Keycode = Keycode::F11;
+    pub const HOTKEY_SCREENSHOT: Keycode = Keycode::F12;
+
+    pub const CHIP8_KEYPAD_BACKGROUND_COLOR: Color = Color::RGB(0x20, 0x20, 0x20);
+    pub const CHIP8_KEYPAD_KEY_COLOR: Color = Color::RGB(0x50, 0x50, 0x50);
+    pub const CHIP8_KEYPAD_LABEL_COLOR: Color = Color::RGB(0xC0, 0xC0, 0xC0);
+    pub const CHIP8_KEYPAD_PRESSED_KEY_COLOR: Color = Color::RGB(0xE0, 0xE0, 0xE0);
+    pub const CHIP8_KEYPAD_PRESSED_LABEL_COLOR: Color = Color::RGB(0x20, 0x20, 0x20);
+
+    pub fn create_key_map() -> HashMap<Keycode, usize> {
+        let mut map = HashMap::with_capacity(super::CHIP8_KEY_COUNT);
+
+        map.insert(Keycode::Num0, 0);
+        map.insert(Keycode::Num1, 1);
+        map.insert(Keycode::Num2, 2);
+        map.insert(Keycode::Num3, 3);
+        map.insert(Keycode::Num4, 4);
+        map.insert(Keycode::Num5, 5);
+        map.insert(Keycode::Num6, 6);
+        map.insert(Keycode::Num7, 7);
+        map.insert(Keycode::Num8, 8);
+        map.insert(Keycode::Num9, 9);
+        map.insert(Keycode::A, 10);
+        map.insert(Keycode::B, 11);
+        map.insert(Keycode::C, 12);
+        map.insert(Keycode::D, 13);
+        map.insert(Keycode::E, 14);
+        map.insert(Keycode::F, 15);
+
+        map
+    }
+}
+
+// Hotkeys and key bindings of the terminal frontend
+#[cfg(feature = "tui")]
+pub use self::tui::*;
+
+#[cfg(feature = "tui")]
+mod tui {
+    use crossterm::event::KeyCode;
+    use std::collections::HashMap;
+
+    pub const TUI_HOTKEY_NEXT_THEME: KeyCode = KeyCode::F(2);
+    pub const TUI_HOTKEY_TOGGLE_PERSISTENCE: KeyCode = KeyCode::F(3);
+
+    pub fn create_tui_key_map() -> HashMap<KeyCode, usize> {
+        let mut map = HashMap::with_capacity(super::CHIP8_KEY_COUNT);
+
+        for key in 0..super::CHIP8_KEY_COUNT {
+            let c = std::char::from_digit(key as u32, 16).unwrap();
+            map.insert(KeyCode::Char(c), key);
+            map.insert(KeyCode::Char(c.to_ascii_uppercase()), key);
+        }
 
-pub fn create_key_map() -> HashMap<Keycode, usize> {
-    let mut map = HashMap::with_capacity(CHIP8_KEY_COUNT);
-
-    map.insert(Keycode::Num0, 0);
-    map.insert(Keycode::Num1, 1);
-    map.insert(Keycode::Num2, 2);
-    map.insert(Keycode::Num3, 3);
-    map.insert(Keycode::Num4, 4);
-    map.insert(Keycode::Num5, 5);
-    map.insert(Keycode::Num6, 6);
-    map.insert(Keycode::Num7, 7);
-    map.insert(Keycode::Num8, 8);
-    map.insert(Keycode::Num9, 9);
-    map.insert(Keycode::A, 10);
-    map.insert(Keycode::B, 11);
-    map.insert(Keycode::C, 12);
-    map.insert(Keycode::D, 13);
-    map.insert(Keycode::E, 14);
-    map.insert(Keycode::F, 15);
-
-    map
-}
\ No newline at end of file
+        map
+    }
+}
@@ -0,0 +1,10 @@
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod capture;
+pub mod environment;
+pub mod headless;
+#[cfg(feature = "sdl")]
+pub mod sdl;
+pub mod trace;
+#[cfg(feature = "tui")]
+pub mod tui;
@@ -0,0 +1,129 @@
+use rayon::prelude::*;
+use std::sync::Arc;
+
+use crate::chip8::engine::Engine;
+use crate::chip8::error::Chip8Error;
+use crate::chip8::quirks::Quirks;
+use crate::chip8::Chip8;
+use crate::config;
+
+const WIDTH: usize = config::CHIP8_WIDTH as usize;
+const PIXEL_COUNT: usize = WIDTH * config::CHIP8_HEIGHT as usize;
+
+struct Machine {
+    chip8: Chip8,
+    // Set when the program failed, the machine then stays stopped until a reset
+    error: Option<Chip8Error>,
+}
+
+// Many independent machines running the same program, stepped in lockstep on rayon's thread
+// pool. Each machine has its own memory and seed, for bulk program analysis and RL rollouts
+pub struct MachineBatch {
+    program: Arc<[u8]>,
+    quirks: Quirks,
+    engine: Engine,
+    machines: Vec<Machine>,
+}
+
+impl MachineBatch {
+    pub fn new(program: Arc<[u8]>, quirks: Quirks, count: usize) -> Result<Self, Chip8Error> {
+        // Check the program once instead of failing in every machine
+        Chip8::new().load(&program)?;
+
+        let mut batch = Self {
+            program,
+            quirks,
+            engine: Engine::Blocks,
+            machines: Vec::with_capacity(count),
+        };
+        batch.machines.resize_with(count, || Machine {
+            chip8: Chip8::new(),
+            error: None,
+        });
+        batch.reset(0);
+
+        Ok(batch)
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn chip8(&self, index: usize) -> &Chip8 {
+        &self.machines[index].chip8
+    }
+
+    // Why the machine stopped, if its program failed
+    pub fn error(&self, index: usize) -> Option<&Chip8Error> {
+        self.machines[index].error.as_ref()
+    }
+
+    // Machines run basic blocks by default, the engine is kept by `reset`
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+        for machine in self.machines.iter_mut() {
+            machine.chip8.set_engine(engine);
+        }
+    }
+
+    // Restarts every machine, machine i is seeded with seed + i
+    pub fn reset(&mut self, seed: u64) {
+        let program = &self.program;
+        let quirks = self.quirks;
+        let engine = self.engine;
+
+        self.machines
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(index, machine)| {
+                machine.chip8 = Chip8::new();
+                machine.chip8.seed(seed.wrapping_add(index as u64));
+                machine.chip8.set_quirks(quirks);
+                machine.chip8.set_engine(engine);
+                machine.chip8.load(program).unwrap();
+                machine.error = None;
+            });
+    }
+
+    // Presses the given keys of a machine and releases the others
+    pub fn hold_keys(&mut self, index: usize, keys: &[usize]) {
+        self.machines[index].chip8.hold_keys(keys);
+    }
+
+    // Runs every machine that hasn't stopped for the given number of frames
+    pub fn run_frames(&mut self, frames: u32) {
+        self.machines.par_iter_mut().for_each(|machine| {
+            for _ in 0..frames {
+                if machine.error.is_some() {
+                    break;
+                }
+                if let Err(err) = machine.chip8.run_frame() {
+                    machine.error = Some(err);
+                }
+            }
+        });
+    }
+
+    // The displays of all machines one after the other, 64x32 bytes each with 1 for lit pixels
+    pub fn framebuffers(&self) -> Vec<u8> {
+        let mut framebuffers = vec![0; self.machines.len() * PIXEL_COUNT];
+
+        framebuffers
+            .par_chunks_mut(PIXEL_COUNT)
+            .zip(self.machines.par_iter())
+            .for_each(|(framebuffer, machine)| {
+                let rows = machine.chip8.screen().rows();
+                for (pixels, row) in framebuffer.chunks_exact_mut(WIDTH).zip(rows.iter()) {
+                    for (x, pixel) in pixels.iter_mut().enumerate() {
+                        *pixel = (row >> (WIDTH - 1 - x) & 1) as u8;
+                    }
+                }
+            });
+
+        framebuffers
+    }
+}
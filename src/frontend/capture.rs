@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+#[cfg(feature = "images")]
+use std::path::PathBuf;
+#[cfg(feature = "images")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::chip8::screen::Screen;
+#[cfg(feature = "images")]
+use crate::config;
+use error::CaptureError;
+#[cfg(feature = "images")]
+use recorder::{Recorder, RecordingFormat};
+use wav::WavWriter;
+
+pub mod error;
+#[cfg(feature = "images")]
+pub mod image;
+#[cfg(feature = "images")]
+pub mod recorder;
+pub mod wav;
+
+// Save the display to a PNG file, using the current palette
+#[cfg(feature = "images")]
+pub fn save_screenshot(screen: &Screen, path: &Path, scale: u32) -> Result<(), CaptureError> {
+    let (width, height) = image::scaled_size(scale).ok_or(CaptureError::ScaleTooLarge)?;
+    let pixels = image::scale_rgb(
+        &screen.pixel_colors(),
+        config::CHIP8_WIDTH as usize,
+        config::CHIP8_HEIGHT as usize,
+        scale as usize,
+    )
+    .ok_or(CaptureError::ScaleTooLarge)?;
+
+    let file = File::create(path)?;
+    image::write_png(BufWriter::new(file), &pixels, width, height)?;
+
+    Ok(())
+}
+
+// Screenshots, frame recordings and audio captures, fed one emulated frame at a time. Without
+// the images feature only audio is captured
+pub struct Captures {
+    sample_rate: u32,
+    #[cfg(feature = "images")]
+    capture_scale: u32,
+    #[cfg(feature = "images")]
+    recording_format: RecordingFormat,
+    #[cfg(feature = "images")]
+    recorder: Option<Recorder>,
+    audio_capture: Option<WavWriter<BufWriter<File>>>,
+}
+
+impl Captures {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            #[cfg(feature = "images")]
+            capture_scale: 1,
+            #[cfg(feature = "images")]
+            recording_format: RecordingFormat::Gif,
+            #[cfg(feature = "images")]
+            recorder: None,
+            audio_capture: None,
+        }
+    }
+
+    // Record the buzzer to a WAV file, in sync with emulated time
+    pub fn start_audio_capture(&mut self, path: &Path) -> Result<(), CaptureError> {
+        self.stop_audio_capture()?;
+        self.audio_capture = Some(self.create_audio_capture(path)?);
+
+        Ok(())
+    }
+
+    pub fn stop_audio_capture(&mut self) -> Result<(), CaptureError> {
+        if let Some(capture) = self.audio_capture.take() {
+            capture.finish()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn stop_all(&mut self) {
+        #[cfg(feature = "images")]
+        if let Err(err) = self.stop_recording() {
+            eprintln!("Could not finish recording: {}", err);
+        }
+        if let Err(err) = self.stop_audio_capture() {
+            eprintln!("Could not finish audio capture: {}", err);
+        }
+    }
+
+    // Must be called once per emulated frame, with the display and the frame's audio
+    pub fn record_frame(&mut self, screen: &Screen, samples: &[f32]) {
+        #[cfg(feature = "images")]
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.record_frame(&screen.pixel_colors(), samples) {
+                eprintln!("Recording stopped: {}", err);
+                self.recorder = None;
+            }
+        }
+        // The display is only recorded with the images feature
+        #[cfg(not(feature = "images"))]
+        let _ = screen;
+
+        if let Some(capture) = &mut self.audio_capture {
+            if let Err(err) = capture.write_samples(samples) {
+                eprintln!("Audio capture stopped: {}", err);
+                self.audio_capture = None;
+            }
+        }
+    }
+
+    fn create_audio_capture(
+        &self,
+        path: &Path,
+    ) -> Result<WavWriter<BufWriter<File>>, CaptureError> {
+        Ok(WavWriter::create(path, self.sample_rate)?)
+    }
+}
+
+#[cfg(feature = "images")]
+impl Captures {
+    // Scale of the screenshots and recordings taken with the hotkeys
+    pub fn set_capture_scale(&mut self, scale: u32) {
+        self.capture_scale = scale;
+    }
+
+    // Format of the recordings started with the recording hotkey
+    pub fn set_recording_format(&mut self, format: RecordingFormat) {
+        self.recording_format = format;
+    }
+
+    pub fn take_screenshot(&self, screen: &Screen) {
+        let path = PathBuf::from(format!("screenshot-{}.png", Self::timestamp()));
+
+        match save_screenshot(screen, &path, self.capture_scale) {
+            Ok(()) => println!("Saved screenshot to {}", path.display()),
+            Err(err) => eprintln!("Could not save screenshot: {}", err),
+        }
+    }
+
+    // Record every frame to a GIF file or to a directory of numbered PNG files,
+    // and the buzzer to a WAV file when an audio path is given
+    pub fn start_recording(
+        &mut self,
+        format: RecordingFormat,
+        path: &Path,
+        audio_path: Option<&Path>,
+    ) -> Result<(), CaptureError> {
+        self.stop_recording()?;
+
+        let audio = match audio_path {
+            Some(audio_path) => Some(self.create_audio_capture(audio_path)?),
+            None => None,
+        };
+        self.recorder = Some(Recorder::start(format, path, self.capture_scale, audio)?);
+
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), CaptureError> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn toggle_recording(&mut self) {
+        if self.recorder.is_some() {
+            match self.stop_recording() {
+                Ok(()) => println!("Recording stopped"),
+                Err(err) => eprintln!("Could not finish recording: {}", err),
+            }
+            return;
+        }
+
+        let mut path = PathBuf::from(format!("recording-{}", Self::timestamp()));
+        let audio_path = path.with_extension("wav");
+        if self.recording_format == RecordingFormat::Gif {
+            path.set_extension("gif");
+        }
+
+        match self.start_recording(self.recording_format, &path, Some(&audio_path)) {
+            Ok(()) => println!("Recording to {}", path.display()),
+            Err(err) => eprintln!("Could not start recording: {}", err),
+        }
+    }
+
+    fn timestamp() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    }
+}
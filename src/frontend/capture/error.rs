@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter, Result};
+use std::io;
+
+// Failures of screenshots, recordings and audio captures, kept apart from the interpreter's
+// errors so the core doesn't depend on the image encoders
+pub enum CaptureError {
+    ScaleTooLarge,
+    Io(io::Error),
+    #[cfg(feature = "images")]
+    ImageEncoding(png::EncodingError),
+    #[cfg(feature = "images")]
+    GifEncoding(gif::EncodingError),
+}
+
+impl CaptureError {
+    fn message(&self) -> &str {
+        match self {
+            CaptureError::ScaleTooLarge => "The capture scale is too large",
+            CaptureError::Io(_) => "I/O error",
+            #[cfg(feature = "images")]
+            CaptureError::ImageEncoding(_) => "Could not encode the image",
+            #[cfg(feature = "images")]
+            CaptureError::GifEncoding(_) => "Could not encode the animation",
+        }
+    }
+}
+
+impl Error for CaptureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CaptureError::Io(err) => Some(err),
+            #[cfg(feature = "images")]
+            CaptureError::ImageEncoding(err) => Some(err),
+            #[cfg(feature = "images")]
+            CaptureError::GifEncoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for CaptureError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self.source() {
+            Some(source) => write!(f, "{}: {}", self.message(), source),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl Debug for CaptureError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl From<io::Error> for CaptureError {
+    fn from(err: io::Error) -> Self {
+        CaptureError::Io(err)
+    }
+}
+
+#[cfg(feature = "images")]
+impl From<png::EncodingError> for CaptureError {
+    fn from(err: png::EncodingError) -> Self {
+        CaptureError::ImageEncoding(err)
+    }
+}
+
+#[cfg(feature = "images")]
+impl From<gif::EncodingError> for CaptureError {
+    fn from(err: gif::EncodingError) -> Self {
+        CaptureError::GifEncoding(err)
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::Write;
+
+use crate::config;
+
+// Size of the display at `scale`, None if it doesn't fit in an image
+pub fn scaled_size(scale: u32) -> Option<(u32, u32)> {
+    let width = config::CHIP8_WIDTH.checked_mul(scale)?;
+    let height = config::CHIP8_HEIGHT.checked_mul(scale)?;
+
+    Some((width, height))
+}
+
+// Nearest-neighbor upscaling of a RGB24 image, None if the scaled image is too large to allocate
+pub fn scale_rgb(pixels: &[u8], width: usize, height: usize, scale: usize) -> Option<Vec<u8>> {
+    if scale == 1 {
+        return Some(pixels.to_vec());
+    }
+
+    let size = pixels.len().checked_mul(scale)?.checked_mul(scale)?;
+    let mut scaled = Vec::with_capacity(size);
+    for row in pixels.chunks_exact(width * 3).take(height) {
+        let mut scaled_row = Vec::with_capacity(row.len() * scale);
+        for color in row.chunks_exact(3) {
+            for _ in 0..scale {
+                scaled_row.extend_from_slice(color);
+            }
+        }
+
+        for _ in 0..scale {
+            scaled.extend_from_slice(&scaled_row);
+        }
+    }
+
+    Some(scaled)
+}
+
+pub fn write_png<W: Write>(
+    writer: W,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()
+}
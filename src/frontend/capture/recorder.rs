@@ -0,0 +1,205 @@
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use super::error::CaptureError;
+use super::image;
+use super::wav::WavWriter;
+use crate::config;
+
+// Speed of the GIF color quantization, only used when a frame has more than 256 colors
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+
+// GIF sizes are 16-bit, every scale accepted on the command line must fit
+const _: () = assert!(config::CHIP8_CAPTURE_MAX_SCALE <= u16::MAX as u32 / config::CHIP8_WIDTH);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordingFormat {
+    Gif,
+    PngSequence,
+}
+
+impl RecordingFormat {
+    pub const ALL: [RecordingFormat; 2] = [RecordingFormat::Gif, RecordingFormat::PngSequence];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::PngSequence => "png",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name)
+    }
+}
+
+enum Output {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        // Identical consecutive frames are merged into one GIF frame with a longer delay
+        pending: Option<Vec<u8>>,
+        pending_start: u64,
+    },
+    PngSequence {
+        directory: PathBuf,
+    },
+}
+
+// Records every presented frame, at 60 frames per second, and optionally the buzzer alongside
+pub struct Recorder {
+    output: Output,
+    audio: Option<WavWriter<BufWriter<File>>>,
+    scale: u32,
+    width: u32,
+    height: u32,
+    frame_count: u64,
+}
+
+impl Recorder {
+    pub fn start(
+        format: RecordingFormat,
+        path: &Path,
+        scale: u32,
+        audio: Option<WavWriter<BufWriter<File>>>,
+    ) -> Result<Self, CaptureError> {
+        let (width, height) = image::scaled_size(scale).ok_or(CaptureError::ScaleTooLarge)?;
+        let output = match format {
+            RecordingFormat::Gif => {
+                let (gif_width, gif_height) = gif_size(width, height)?;
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = gif::Encoder::new(file, gif_width, gif_height, &[])?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+
+                Output::Gif {
+                    encoder,
+                    pending: None,
+                    pending_start: 0,
+                }
+            }
+            RecordingFormat::PngSequence => {
+                fs::create_dir_all(path)?;
+
+                Output::PngSequence {
+                    directory: path.to_path_buf(),
+                }
+            }
+        };
+
+        Ok(Self {
+            output,
+            audio,
+            scale,
+            width,
+            height,
+            frame_count: 0,
+        })
+    }
+
+    pub fn record_frame(&mut self, pixels: &[u8], samples: &[f32]) -> Result<(), CaptureError> {
+        if let Some(audio) = &mut self.audio {
+            audio.write_samples(samples)?;
+        }
+
+        let frame = image::scale_rgb(
+            pixels,
+            config::CHIP8_WIDTH as usize,
+            config::CHIP8_HEIGHT as usize,
+            self.scale as usize,
+        )
+        .ok_or(CaptureError::ScaleTooLarge)?;
+
+        match &mut self.output {
+            Output::Gif {
+                encoder,
+                pending,
+                pending_start,
+            } => {
+                if pending.as_ref() != Some(&frame) {
+                    if let Some(previous) = pending.take() {
+                        Self::write_gif_frame(
+                            encoder,
+                            &previous,
+                            (self.width, self.height),
+                            *pending_start,
+                            self.frame_count,
+                        )?;
+                    }
+
+                    *pending = Some(frame);
+                    *pending_start = self.frame_count;
+                }
+            }
+            Output::PngSequence { directory } => {
+                let path = directory.join(format!("frame-{:06}.png", self.frame_count));
+                image::write_png(
+                    BufWriter::new(File::create(path)?),
+                    &frame,
+                    self.width,
+                    self.height,
+                )?;
+            }
+        }
+
+        self.frame_count += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), CaptureError> {
+        if let Output::Gif {
+            encoder,
+            pending,
+            pending_start,
+        } = &mut self.output
+        {
+            if let Some(previous) = pending.take() {
+                Self::write_gif_frame(
+                    encoder,
+                    &previous,
+                    (self.width, self.height),
+                    *pending_start,
+                    self.frame_count,
+                )?;
+            }
+        }
+
+        if let Some(audio) = self.audio {
+            audio.finish()?;
+        }
+
+        Ok(())
+    }
+
+    // GIF delays are in hundredths of a second, round the frame boundaries so the total duration
+    // stays in sync with the 60 Hz frame rate
+    fn write_gif_frame(
+        encoder: &mut gif::Encoder<BufWriter<File>>,
+        pixels: &[u8],
+        (width, height): (u32, u32),
+        start_frame: u64,
+        end_frame: u64,
+    ) -> Result<(), CaptureError> {
+        let to_centiseconds =
+            |frame: u64| (frame as f64 * config::CHIP8_FRAME_FREQ * 100.0).round() as u64;
+        let delay = to_centiseconds(end_frame) - to_centiseconds(start_frame);
+
+        let (width, height) = gif_size(width, height)?;
+        let mut frame = gif::Frame::from_rgb_speed(width, height, pixels, GIF_QUANTIZATION_SPEED);
+        frame.delay = delay.min(u16::MAX as u64) as u16;
+        encoder.write_frame(&frame)?;
+
+        Ok(())
+    }
+}
+
+fn gif_size(width: u32, height: u32) -> Result<(u16, u16), CaptureError> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(CaptureError::ScaleTooLarge),
+    }
+}
@@ -0,0 +1,115 @@
+use crate::chip8::quirks::Quirks;
+use crate::chip8::Chip8;
+use crate::config;
+
+// Reads the machine after a step
+pub type Probe<T> = Box<dyn Fn(&Chip8) -> T>;
+
+// What an agent plays: the program, and how its score and end are read from the machine. The
+// score is usually a BCD number the program keeps in memory, see `read_bcd`
+pub struct Game {
+    program: Vec<u8>,
+    quirks: Quirks,
+    score: Probe<f32>,
+    done: Probe<bool>,
+}
+
+impl Game {
+    pub fn new(program: Vec<u8>, score: Probe<f32>, done: Probe<bool>) -> Self {
+        Self {
+            program,
+            quirks: Quirks::default(),
+            score,
+            done,
+        }
+    }
+
+    pub fn with_quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+}
+
+// Number stored one decimal digit per byte from `address`, as written by Fx33
+pub fn read_bcd(chip8: &Chip8, address: usize, digits: usize) -> u32 {
+    (0..digits).fold(0, |number, digit| {
+        let byte = chip8.memory[(address + digit) % config::CHIP8_MEMORY_SIZE];
+        number * 10 + byte as u32
+    })
+}
+
+// Gym-style environment for reinforcement learning: the agent picks the keys held during each
+// step and gets the display, the reward and whether the episode is over. Runs as fast as the
+// interpreter, without a display or audio
+pub struct Environment {
+    game: Game,
+    chip8: Chip8,
+    // The display as last observed
+    observation: Vec<bool>,
+    frames_per_step: u32,
+    score: f32,
+    done: bool,
+}
+
+impl Environment {
+    pub fn new(game: Game) -> Self {
+        let mut environment = Self {
+            game,
+            chip8: Chip8::new(),
+            observation: Vec::new(),
+            frames_per_step: 1,
+            score: 0.0,
+            done: false,
+        };
+        environment.reset(0);
+
+        environment
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    // Frames run with the same keys held in each step, at least 1
+    pub fn set_frames_per_step(&mut self, frames: u32) {
+        self.frames_per_step = frames.max(1);
+    }
+
+    // Starts a new episode, returns the first observation
+    pub fn reset(&mut self, seed: u64) -> &[bool] {
+        self.chip8 = Chip8::new();
+        self.chip8.seed(seed);
+        self.chip8.set_quirks(self.game.quirks);
+        // A program too large ends every episode at once
+        self.done = self.chip8.load(&self.game.program).is_err();
+        self.score = (self.game.score)(&self.chip8);
+        self.observation = self.chip8.screen().pixel_states();
+
+        &self.observation
+    }
+
+    // Holds `keys` for a step. Returns the display as 64x32 pixels row by row, the change of
+    // score and whether the episode is over, which it also is when the program fails
+    pub fn step(&mut self, keys: &[usize]) -> (&[bool], f32, bool) {
+        if self.done {
+            return (&self.observation, 0.0, true);
+        }
+
+        self.chip8.hold_keys(keys);
+
+        for _ in 0..self.frames_per_step {
+            if self.chip8.run_frame().is_err() {
+                self.done = true;
+                break;
+            }
+        }
+
+        let score = (self.game.score)(&self.chip8);
+        let reward = score - self.score;
+        self.score = score;
+        self.done = self.done || (self.game.done)(&self.chip8);
+        self.observation = self.chip8.screen().pixel_states();
+
+        (&self.observation, reward, self.done)
+    }
+}
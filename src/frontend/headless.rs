@@ -0,0 +1,163 @@
+use std::fmt::Write;
+
+use super::capture::Captures;
+use crate::chip8::audio::Buzzer;
+use crate::chip8::error::Chip8Error;
+use crate::chip8::Chip8;
+use crate::config;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct KeyEvent {
+    frame: u64,
+    key: usize,
+    pressed: bool,
+}
+
+// Key presses applied at given frames. Each line is "<frame> <key> down|up" with the key as a
+// hex digit, and '#' starts a comment
+#[derive(Debug, Default)]
+pub struct KeyScript {
+    events: Vec<KeyEvent>,
+}
+
+impl KeyScript {
+    pub fn parse(script: &str) -> Result<Self, String> {
+        let mut events = Vec::new();
+
+        for (number, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let event =
+                Self::parse_line(line).map_err(|err| format!("Line {}: {}", number + 1, err))?;
+            events.push(event);
+        }
+
+        // The sort is stable, events of the same frame are applied in the script's order
+        events.sort_by_key(|event| event.frame);
+
+        Ok(Self { events })
+    }
+
+    fn parse_line(line: &str) -> Result<KeyEvent, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(format!("Expected '<frame> <key> down|up', got '{}'", line));
+        }
+
+        let frame = fields[0]
+            .parse()
+            .map_err(|_| format!("'{}' is not a frame number", fields[0]))?;
+        let key = match usize::from_str_radix(fields[1], 16) {
+            Ok(key) if key < config::CHIP8_KEY_COUNT => key,
+            _ => return Err(format!("'{}' is not a key between 0 and F", fields[1])),
+        };
+        let pressed = match fields[2] {
+            "down" => true,
+            "up" => false,
+            action => return Err(format!("'{}' is not down or up", action)),
+        };
+
+        Ok(KeyEvent {
+            frame,
+            key,
+            pressed,
+        })
+    }
+}
+
+// Runs a program for a fixed number of frames without a display or an audio device
+pub struct Headless {
+    chip8: Chip8,
+    buzzer: Buzzer,
+    captures: Captures,
+    keys: KeyScript,
+}
+
+impl Headless {
+    pub fn new(chip8: Chip8, buzzer: Buzzer, keys: KeyScript) -> Self {
+        let captures = Captures::new(buzzer.sample_rate());
+
+        Self {
+            chip8,
+            buzzer,
+            captures,
+            keys,
+        }
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    pub fn captures_mut(&mut self) -> &mut Captures {
+        &mut self.captures
+    }
+
+    // Stops early if the program fails
+    pub fn run(&mut self, frames: u64) -> Result<(), Chip8Error> {
+        self.run_traced(frames, &mut |_| Ok(()))
+    }
+
+    // Like `run`, calling `trace` before every executed instruction
+    pub fn run_traced(
+        &mut self,
+        frames: u64,
+        trace: &mut dyn FnMut(&Chip8) -> Result<(), Chip8Error>,
+    ) -> Result<(), Chip8Error> {
+        let result = self.run_frames(frames, trace);
+        self.captures.stop_all();
+
+        result
+    }
+
+    fn run_frames(
+        &mut self,
+        frames: u64,
+        trace: &mut dyn FnMut(&Chip8) -> Result<(), Chip8Error>,
+    ) -> Result<(), Chip8Error> {
+        let mut events = self.keys.events.iter().peekable();
+
+        for frame in 0..frames {
+            while let Some(event) = events.next_if(|event| event.frame == frame) {
+                if event.pressed {
+                    self.chip8.key_down(event.key);
+                } else {
+                    self.chip8.key_up(event.key);
+                }
+            }
+
+            let sound_playing = self.chip8.run_frame_traced(trace)?;
+            let samples = self.buzzer.render_frame(sound_playing);
+            self.captures.record_frame(self.chip8.screen(), samples);
+        }
+
+        Ok(())
+    }
+
+    // Registers and screen hash, in a format meant to be compared between runs
+    pub fn report(&self) -> String {
+        let registers = &self.chip8.registers;
+        let mut report = String::new();
+
+        writeln!(
+            report,
+            "PC={:04X} I={:04X} SP={:02X} DT={:02X} ST={:02X}",
+            registers.pc, registers.i, registers.sp, registers.dt, registers.st
+        )
+        .unwrap();
+        for (i, v) in registers.v.iter().enumerate() {
+            let separator = if i + 1 == registers.v.len() {
+                '\n'
+            } else {
+                ' '
+            };
+            write!(report, "V{:X}={:02X}{}", i, v, separator).unwrap();
+        }
+        writeln!(report, "Screen={:016X}", self.chip8.screen().hash()).unwrap();
+
+        report
+    }
+}
@@ -0,0 +1,437 @@
+use sdl2::audio::AudioDevice;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::{FullscreenType, Window};
+use sdl2::EventPump;
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::capture::Captures;
+use crate::chip8::audio::Buzzer;
+use crate::chip8::error::Chip8Error;
+use crate::chip8::palette::Theme;
+use crate::chip8::Chip8;
+use crate::config;
+use audio::{AudioStream, SampleQueue};
+use crt::{CrtEffects, CrtFilter};
+use keypad::Keypad;
+use scaling::ScaleMode;
+
+pub mod audio;
+pub mod crt;
+mod keypad;
+pub mod scaling;
+
+// Window, keyboard and audio device around the interpreter
+pub struct Sdl {
+    chip8: Chip8,
+    key_map: HashMap<Keycode, usize>,
+    keypad: Option<Keypad>,
+    theme: Theme,
+    crt_effects: CrtEffects,
+    crt: Option<CrtFilter>,
+    scale_mode: ScaleMode,
+    captures: Captures,
+    canvas: Canvas<Window>,
+    audio_device: AudioDevice<AudioStream>,
+    buzzer: Buzzer,
+    sample_queue: SampleQueue,
+
+    frame_duration: Duration,
+}
+
+impl Sdl {
+    pub fn new(
+        chip8: Chip8,
+        canvas: Canvas<Window>,
+        mut audio_device: AudioDevice<AudioStream>,
+        buzzer: Buzzer,
+    ) -> Self {
+        let sample_queue = audio_device.lock().queue();
+        let captures = Captures::new(buzzer.sample_rate());
+
+        Self {
+            chip8,
+            key_map: config::create_key_map(),
+            keypad: None,
+            theme: Theme::Classic,
+            crt_effects: CrtEffects::all(),
+            crt: None,
+            scale_mode: ScaleMode::Integer,
+            captures,
+            canvas,
+            audio_device,
+            buzzer,
+            sample_queue,
+
+            frame_duration: Duration::from_secs(0),
+        }
+    }
+
+    pub fn captures_mut(&mut self) -> &mut Captures {
+        &mut self.captures
+    }
+
+    // Theme the theme hotkey cycles from. The interpreter's palette is kept, so colors given on
+    // the command line stay until the theme changes
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn next_theme(&mut self) {
+        self.theme = self.theme.next();
+        self.chip8.set_palette(self.theme.palette());
+    }
+
+    // Post-process the display with the given CRT effects, None disables the filter
+    pub fn set_crt_filter(&mut self, effects: Option<CrtEffects>) {
+        if let Some(effects) = effects {
+            self.crt_effects = effects;
+        }
+
+        self.crt = effects.map(CrtFilter::new);
+    }
+
+    fn toggle_crt_filter(&mut self) {
+        if self.crt.is_some() {
+            self.set_crt_filter(None);
+        } else {
+            self.set_crt_filter(Some(self.crt_effects));
+        }
+    }
+
+    // Show the on-screen keypad to the right of the display, the window must be wide enough
+    pub fn show_keypad(&mut self) {
+        let (_, keypad_area) = self.layout(true);
+        self.keypad = keypad_area.map(Keypad::new);
+    }
+
+    pub fn set_scale_mode(&mut self, mode: ScaleMode) {
+        self.scale_mode = mode;
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.buzzer.set_volume(volume);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.buzzer.set_muted(muted);
+    }
+
+    fn change_volume(&mut self, delta: f32) {
+        self.set_volume(self.buzzer.volume() + delta);
+    }
+
+    fn toggle_mute(&mut self) {
+        self.set_muted(!self.buzzer.muted());
+    }
+
+    fn toggle_persistence(&mut self) {
+        let enabled = self.chip8.screen().persistence();
+        self.chip8.set_persistence(!enabled);
+    }
+
+    // Advance emulated time by one frame and queue the frame's audio
+    fn frame(&mut self) -> Result<(), Chip8Error> {
+        let sound_playing = self.chip8.run_frame()?;
+
+        let samples = self.buzzer.render_frame(sound_playing);
+        self.sample_queue.push(samples);
+
+        self.captures.record_frame(self.chip8.screen(), samples);
+
+        Ok(())
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+
+        window.set_fullscreen(fullscreen).unwrap();
+    }
+
+    pub fn run(&mut self, event_pump: &mut EventPump) {
+        let texture_creator = self.canvas.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_streaming(
+                PixelFormatEnum::RGB24,
+                config::CHIP8_WIDTH,
+                config::CHIP8_HEIGHT,
+            )
+            .unwrap();
+        let mut crt_texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, crt::CRT_WIDTH, crt::CRT_HEIGHT)
+            .unwrap();
+
+        // Revision of the display in the textures. The display is only uploaded when it
+        // changed, and the window only presented then or when its size, layout or keypad changed
+        let mut uploaded_revision = None;
+        let mut redraw = true;
+
+        // The audio device plays the samples queued by every frame from now on
+        self.audio_device.resume();
+        let mut last_update = Instant::now();
+
+        'running: loop {
+            for event in event_pump.poll_iter() {
+                let mut key_states = [false; config::CHIP8_KEY_COUNT];
+                key_states.copy_from_slice(self.chip8.key_states());
+
+                match event {
+                    Event::Quit { .. }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::Escape),
+                        ..
+                    } => break 'running,
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_NEXT_THEME),
+                        ..
+                    } => self.next_theme(),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_TOGGLE_PERSISTENCE),
+                        ..
+                    } => self.toggle_persistence(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Return),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => self.toggle_fullscreen(),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_NEXT_SCALE_MODE),
+                        ..
+                    } => {
+                        self.set_scale_mode(self.scale_mode.next());
+                        redraw = true;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_TOGGLE_MUTE),
+                        ..
+                    } => self.toggle_mute(),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_VOLUME_DOWN),
+                        ..
+                    } => self.change_volume(-config::CHIP8_SOUND_VOLUME_STEP),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_VOLUME_UP),
+                        ..
+                    } => self.change_volume(config::CHIP8_SOUND_VOLUME_STEP),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_SCREENSHOT),
+                        ..
+                    } => self.captures.take_screenshot(self.chip8.screen()),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_TOGGLE_RECORDING),
+                        ..
+                    } => self.captures.toggle_recording(),
+                    Event::KeyDown {
+                        keycode: Some(config::HOTKEY_TOGGLE_CRT),
+                        ..
+                    } => {
+                        self.toggle_crt_filter();
+                        uploaded_revision = None;
+                    }
+                    Event::KeyDown {
+                        keycode: Some(key), ..
+                    } => {
+                        if let Some(key) = self.key_map.get(&key) {
+                            self.chip8.key_down(*key);
+                        }
+                    }
+                    Event::KeyUp {
+                        keycode: Some(key), ..
+                    } => {
+                        if let Some(key) = self.key_map.get(&key) {
+                            self.chip8.key_up(*key);
+                        }
+                    }
+                    Event::Window {
+                        win_event:
+                            WindowEvent::Resized(..)
+                            | WindowEvent::SizeChanged(..)
+                            | WindowEvent::Exposed,
+                        ..
+                    } => redraw = true,
+                    event => self.handle_pointer_event(&event),
+                }
+
+                // The keypad shows the keys held
+                if self.keypad.is_some() && self.chip8.key_states() != key_states {
+                    redraw = true;
+                }
+            }
+
+            let now = Instant::now();
+            let new_frame = match self.update_frame_timer(now - last_update) {
+                Ok(new_frame) => new_frame,
+                Err(err) => {
+                    eprintln!("The program stopped: {}", err);
+                    break 'running;
+                }
+            };
+            last_update = now;
+
+            let revision = self.chip8.screen().revision();
+            if redraw || uploaded_revision != Some(revision) {
+                if uploaded_revision != Some(revision) {
+                    self.upload(&mut texture, &mut crt_texture);
+                    uploaded_revision = Some(revision);
+                }
+                self.draw(&texture, &crt_texture);
+                redraw = false;
+            }
+
+            // Nothing to do until the next frame or event
+            if !new_frame {
+                thread::sleep(self.time_to_next_frame());
+            }
+        }
+
+        self.captures.stop_all();
+    }
+
+    // Copy the display to the texture shown, through the CRT filter when enabled
+    fn upload(&mut self, texture: &mut Texture, crt_texture: &mut Texture) {
+        let pixel_colors = self.chip8.screen().pixel_colors();
+        if let Some(crt) = &mut self.crt {
+            let pixels = crt.apply(&pixel_colors);
+            crt_texture
+                .update(None, pixels, crt::CRT_WIDTH as usize * 3)
+                .unwrap();
+        } else {
+            texture
+                .update(None, &pixel_colors, config::CHIP8_WIDTH as usize * 3)
+                .unwrap();
+        }
+    }
+
+    fn draw(&mut self, texture: &Texture, crt_texture: &Texture) {
+        let [r, g, b] = self.chip8.screen().palette().background();
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+
+        let (display_area, keypad_area) = self.layout(self.keypad.is_some());
+        let display_rect = self.scale_mode.fit(display_area);
+        if let (Some(keypad), Some(area)) = (&mut self.keypad, keypad_area) {
+            keypad.set_area(area);
+        }
+
+        let texture = if self.crt.is_some() {
+            crt_texture
+        } else {
+            texture
+        };
+        self.canvas.copy(texture, None, display_rect).unwrap();
+
+        if let Some(keypad) = &self.keypad {
+            keypad
+                .draw(&mut self.canvas, self.chip8.key_states())
+                .unwrap();
+        }
+
+        self.canvas.present();
+    }
+
+    // Split the window between the display and the keypad, which gets at most a third of the width
+    fn layout(&self, with_keypad: bool) -> (Rect, Option<Rect>) {
+        let (width, height) = self.canvas.output_size().unwrap();
+
+        if !with_keypad {
+            return (Rect::new(0, 0, width, height), None);
+        }
+
+        let keypad_width = (width / 3).min(height);
+        let display_width = width - keypad_width;
+
+        (
+            Rect::new(0, 0, display_width, height),
+            Some(Rect::new(display_width as i32, 0, keypad_width, height)),
+        )
+    }
+
+    // Press or release keys clicked or touched on the keypad
+    fn handle_pointer_event(&mut self, event: &Event) {
+        let keypad = match self.keypad.as_mut() {
+            Some(keypad) => keypad,
+            None => return,
+        };
+
+        match *event {
+            Event::MouseButtonDown {
+                which,
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if !Keypad::is_synthesized_mouse(which) => {
+                if let Some(key) = keypad.mouse_down(x, y) {
+                    self.chip8.key_down(key);
+                }
+            }
+            Event::MouseButtonUp {
+                which,
+                mouse_btn: MouseButton::Left,
+                ..
+            } if !Keypad::is_synthesized_mouse(which) => {
+                if let Some(key) = keypad.mouse_up() {
+                    self.chip8.key_up(key);
+                }
+            }
+            Event::FingerDown {
+                finger_id, x, y, ..
+            } => {
+                // Touch positions are normalized to the window size
+                let (width, height) = match self.canvas.output_size() {
+                    Ok(size) => size,
+                    Err(_) => return,
+                };
+                let key = keypad.pointer_down(
+                    finger_id,
+                    (x * width as f32) as i32,
+                    (y * height as f32) as i32,
+                );
+                if let Some(key) = key {
+                    self.chip8.key_down(key);
+                }
+            }
+            Event::FingerUp { finger_id, .. } => {
+                if let Some(key) = keypad.pointer_up(finger_id) {
+                    self.chip8.key_up(key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn time_to_next_frame(&self) -> Duration {
+        let frame_period = Duration::from_secs_f64(config::CHIP8_FRAME_FREQ);
+        frame_period.saturating_sub(self.frame_duration)
+    }
+
+    // Returns true when a new frame starts
+    fn update_frame_timer(&mut self, delta: Duration) -> Result<bool, Chip8Error> {
+        let frame_period = Duration::from_secs_f64(config::CHIP8_FRAME_FREQ);
+
+        self.frame_duration += delta;
+        if self.frame_duration < frame_period {
+            return Ok(false);
+        }
+
+        // Keep the frames in step with the audio device, unless far behind
+        self.frame_duration -= frame_period;
+        if self.frame_duration >= frame_period * config::CHIP8_MAX_FRAME_LAG {
+            self.frame_duration = Duration::from_secs(0);
+        }
+
+        self.frame()?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,93 @@
+use sdl2::audio::AudioCallback;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Samples rendered by the emulator, in a fixed-size ring shared with the audio callback. The
+// emulator thread pushes and the audio callback pops, without locking or allocating
+#[derive(Clone)]
+pub struct SampleQueue {
+    ring: Arc<Ring>,
+}
+
+struct Ring {
+    // Samples stored as their bits
+    samples: Box<[AtomicU32]>,
+    // Counts of samples popped and pushed since the start, wrapping around
+    read: AtomicUsize,
+    write: AtomicUsize,
+}
+
+impl SampleQueue {
+    pub fn new(capacity: usize) -> Self {
+        let samples = (0..capacity.max(1)).map(|_| AtomicU32::new(0)).collect();
+
+        Self {
+            ring: Arc::new(Ring {
+                samples,
+                read: AtomicUsize::new(0),
+                write: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    // Samples that don't fit, when the emulator is too far ahead of the audio device, are
+    // dropped
+    pub fn push(&self, samples: &[f32]) {
+        let ring = &self.ring;
+        let capacity = ring.samples.len();
+        let write = ring.write.load(Ordering::Relaxed);
+        let read = ring.read.load(Ordering::Acquire);
+
+        let free = capacity - write.wrapping_sub(read);
+        for (i, sample) in samples.iter().take(free).enumerate() {
+            ring.samples[write.wrapping_add(i) % capacity]
+                .store(sample.to_bits(), Ordering::Relaxed);
+        }
+
+        ring.write.store(
+            write.wrapping_add(samples.len().min(free)),
+            Ordering::Release,
+        );
+    }
+
+    fn pop_into(&self, out: &mut [f32]) {
+        let ring = &self.ring;
+        let capacity = ring.samples.len();
+        let read = ring.read.load(Ordering::Relaxed);
+        let write = ring.write.load(Ordering::Acquire);
+
+        let count = write.wrapping_sub(read).min(out.len());
+        for (i, x) in out[..count].iter_mut().enumerate() {
+            *x = f32::from_bits(
+                ring.samples[read.wrapping_add(i) % capacity].load(Ordering::Relaxed),
+            );
+        }
+        ring.read.store(read.wrapping_add(count), Ordering::Release);
+
+        // Output silence if the emulator falls behind
+        out[count..].iter_mut().for_each(|x| *x = 0.0);
+    }
+}
+
+// Audio callback playing the samples rendered from emulated frames
+pub struct AudioStream {
+    queue: SampleQueue,
+}
+
+impl AudioStream {
+    pub fn new(queue: SampleQueue) -> Self {
+        Self { queue }
+    }
+
+    pub fn queue(&self) -> SampleQueue {
+        self.queue.clone()
+    }
+}
+
+impl AudioCallback for AudioStream {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [Self::Channel]) {
+        self.queue.pop_into(out);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::config;
+
+const SOURCE_WIDTH: usize = config::CHIP8_WIDTH as usize;
+const SCALE: usize = config::CHIP8_CRT_SCALE_FACTOR as usize;
+
+pub const CRT_WIDTH: u32 = config::CHIP8_WIDTH * config::CHIP8_CRT_SCALE_FACTOR;
+pub const CRT_HEIGHT: u32 = config::CHIP8_HEIGHT * config::CHIP8_CRT_SCALE_FACTOR;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrtEffects {
+    pub scanlines: bool,
+    pub pixel_grid: bool,
+    pub bloom: bool,
+    pub curvature: bool,
+}
+
+impl CrtEffects {
+    pub fn all() -> Self {
+        Self {
+            scanlines: true,
+            pixel_grid: true,
+            bloom: true,
+            curvature: true,
+        }
+    }
+
+    // Parses a comma separated list of effect names, e.g. "scanlines,bloom"
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut effects = Self {
+            scanlines: false,
+            pixel_grid: false,
+            bloom: false,
+            curvature: false,
+        };
+
+        for name in list.split(',').map(str::trim) {
+            match name {
+                "scanlines" => effects.scanlines = true,
+                "grid" => effects.pixel_grid = true,
+                "bloom" => effects.bloom = true,
+                "curvature" => effects.curvature = true,
+                "all" => effects = Self::all(),
+                _ => return Err(format!("Unknown CRT effect '{}'", name)),
+            }
+        }
+
+        Ok(effects)
+    }
+}
+
+// Software CRT post-processing of the RGB24 display, upscaled by CHIP8_CRT_SCALE_FACTOR
+pub struct CrtFilter {
+    effects: CrtEffects,
+    image: Vec<f32>,
+    glow: Vec<f32>,
+    blur_scratch: Vec<f32>,
+    output: Vec<u8>,
+}
+
+impl CrtFilter {
+    pub fn new(effects: CrtEffects) -> Self {
+        let size = CRT_WIDTH as usize * CRT_HEIGHT as usize * 3;
+
+        Self {
+            effects,
+            image: vec![0.0; size],
+            glow: vec![0.0; size],
+            blur_scratch: vec![0.0; size],
+            output: vec![0; size],
+        }
+    }
+
+    // Returns the filtered frame as RGB24, CRT_WIDTH x CRT_HEIGHT pixels
+    pub fn apply(&mut self, pixels: &[u8]) -> &[u8] {
+        self.upscale(pixels);
+
+        if self.effects.bloom {
+            Self::box_blur(
+                &self.image,
+                &mut self.glow,
+                &mut self.blur_scratch,
+                config::CHIP8_CRT_BLOOM_RADIUS,
+            );
+        }
+
+        self.apply_masks();
+
+        if self.effects.bloom {
+            for (pixel, glow) in self.image.iter_mut().zip(self.glow.iter()) {
+                *pixel += glow * config::CHIP8_CRT_BLOOM_STRENGTH;
+            }
+        }
+
+        self.write_output();
+
+        &self.output[..]
+    }
+
+    fn upscale(&mut self, pixels: &[u8]) {
+        let width = CRT_WIDTH as usize;
+
+        for y in 0..CRT_HEIGHT as usize {
+            for x in 0..width {
+                let src = ((y / SCALE) * SOURCE_WIDTH + x / SCALE) * 3;
+                let dst = (y * width + x) * 3;
+
+                for c in 0..3 {
+                    self.image[dst + c] = pixels[src + c] as f32 / 255.0;
+                }
+            }
+        }
+    }
+
+    // Darken the scanline gaps and the borders between pixels
+    fn apply_masks(&mut self) {
+        if !self.effects.scanlines && !self.effects.pixel_grid {
+            return;
+        }
+
+        let width = CRT_WIDTH as usize;
+
+        for y in 0..CRT_HEIGHT as usize {
+            for x in 0..width {
+                let mut factor = 1.0;
+                if self.effects.scanlines && y % 2 == 1 {
+                    factor *= config::CHIP8_CRT_SCANLINE_INTENSITY;
+                }
+                if self.effects.pixel_grid && (x % SCALE == SCALE - 1 || y % SCALE == SCALE - 1) {
+                    factor *= config::CHIP8_CRT_GRID_INTENSITY;
+                }
+
+                let index = (y * width + x) * 3;
+                for value in self.image[index..index + 3].iter_mut() {
+                    *value *= factor;
+                }
+            }
+        }
+    }
+
+    // Separable box blur with a sliding sum, pixels outside the image count as black
+    fn box_blur(src: &[f32], dst: &mut [f32], scratch: &mut [f32], radius: usize) {
+        let width = CRT_WIDTH as usize;
+        let height = CRT_HEIGHT as usize;
+
+        Self::blur_pass(src, scratch, width, height, 3, width * 3, radius);
+        Self::blur_pass(scratch, dst, height, width, width * 3, 3, radius);
+    }
+
+    // Blur `lines` lines of `len` pixels, `step` and `line_step` are offsets in the RGB buffer
+    fn blur_pass(
+        src: &[f32],
+        dst: &mut [f32],
+        len: usize,
+        lines: usize,
+        step: usize,
+        line_step: usize,
+        radius: usize,
+    ) {
+        let window = (2 * radius + 1) as f32;
+
+        for line in 0..lines {
+            let start = line * line_step;
+
+            for c in 0..3 {
+                let at = |i: usize| src[start + i * step + c];
+
+                let mut sum: f32 = (0..radius.min(len)).map(at).sum();
+                for i in 0..len {
+                    if i + radius < len {
+                        sum += at(i + radius);
+                    }
+                    if i > radius {
+                        sum -= at(i - radius - 1);
+                    }
+
+                    dst[start + i * step + c] = sum / window;
+                }
+            }
+        }
+    }
+
+    fn write_output(&mut self) {
+        let width = CRT_WIDTH as usize;
+        let height = CRT_HEIGHT as usize;
+
+        for y in 0..height {
+            for x in 0..width {
+                let dst = (y * width + x) * 3;
+
+                let src = if self.effects.curvature {
+                    Self::curve(x, y)
+                } else {
+                    Some((x, y))
+                };
+
+                match src {
+                    Some((sx, sy)) => {
+                        let src = (sy * width + sx) * 3;
+                        for c in 0..3 {
+                            self.output[dst + c] =
+                                (self.image[src + c].min(1.0) * 255.0).round() as u8;
+                        }
+                    }
+                    None => self.output[dst..dst + 3].copy_from_slice(&[0; 3]),
+                }
+            }
+        }
+    }
+
+    // Barrel distortion: returns the source pixel seen at (x, y), or None outside the tube
+    fn curve(x: usize, y: usize) -> Option<(usize, usize)> {
+        let width = CRT_WIDTH as f32;
+        let height = CRT_HEIGHT as f32;
+
+        let nx = (x as f32 + 0.5) / width * 2.0 - 1.0;
+        let ny = (y as f32 + 0.5) / height * 2.0 - 1.0;
+        let distortion = 1.0 + config::CHIP8_CRT_CURVATURE * (nx * nx + ny * ny);
+        let (cx, cy) = (nx * distortion, ny * distortion);
+
+        if cx.abs() >= 1.0 || cy.abs() >= 1.0 {
+            return None;
+        }
+
+        Some((
+            ((cx + 1.0) / 2.0 * width) as usize,
+            ((cy + 1.0) / 2.0 * height) as usize,
+        ))
+    }
+}
@@ -0,0 +1,163 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use std::collections::HashMap;
+
+use crate::chip8::character::DEFAULT_CHARACTER_SET;
+use crate::config;
+
+// Layout of the COSMAC VIP hex keypad
+const KEYPAD_LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// SDL reports mouse events synthesized from touch input with this mouse id
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+
+// Mouse button presses are tracked with this pointer id, touches with their finger id
+const MOUSE_POINTER_ID: i64 = -1;
+
+#[derive(Debug)]
+pub struct Keypad {
+    area: Rect,
+    pressed_by_pointer: HashMap<i64, usize>,
+}
+
+impl Keypad {
+    pub fn new(area: Rect) -> Self {
+        Self {
+            area,
+            pressed_by_pointer: HashMap::new(),
+        }
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    pub fn is_synthesized_mouse(which: u32) -> bool {
+        which == TOUCH_MOUSE_ID
+    }
+
+    pub fn mouse_down(&mut self, x: i32, y: i32) -> Option<usize> {
+        self.pointer_down(MOUSE_POINTER_ID, x, y)
+    }
+
+    pub fn mouse_up(&mut self) -> Option<usize> {
+        self.pointer_up(MOUSE_POINTER_ID)
+    }
+
+    pub fn pointer_down(&mut self, pointer: i64, x: i32, y: i32) -> Option<usize> {
+        let key = self.key_at(x, y)?;
+        self.pressed_by_pointer.insert(pointer, key);
+
+        Some(key)
+    }
+
+    pub fn pointer_up(&mut self, pointer: i64) -> Option<usize> {
+        self.pressed_by_pointer.remove(&pointer)
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, key_states: &[bool]) -> Result<(), String> {
+        canvas.set_draw_color(config::CHIP8_KEYPAD_BACKGROUND_COLOR);
+        canvas.fill_rect(self.area)?;
+
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                let cell = self.key_rect(row, col);
+                let (key_color, label_color) = if key_states[*key] {
+                    (
+                        config::CHIP8_KEYPAD_PRESSED_KEY_COLOR,
+                        config::CHIP8_KEYPAD_PRESSED_LABEL_COLOR,
+                    )
+                } else {
+                    (
+                        config::CHIP8_KEYPAD_KEY_COLOR,
+                        config::CHIP8_KEYPAD_LABEL_COLOR,
+                    )
+                };
+
+                let margin = cell.width() as i32 / 16;
+                canvas.set_draw_color(key_color);
+                canvas.fill_rect(Rect::new(
+                    cell.x() + margin,
+                    cell.y() + margin,
+                    cell.width() - 2 * margin as u32,
+                    cell.height() - 2 * margin as u32,
+                ))?;
+
+                Self::draw_label(canvas, cell, *key, label_color)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Draw the key's hex digit using the built-in font sprites
+    fn draw_label(
+        canvas: &mut Canvas<Window>,
+        cell: Rect,
+        key: usize,
+        color: Color,
+    ) -> Result<(), String> {
+        let sprite_height = config::CHIP8_DEFAULT_SPRITE_HEIGHT as usize;
+        let sprite = &DEFAULT_CHARACTER_SET[key * sprite_height..(key + 1) * sprite_height];
+
+        // Font sprites are 4 pixels wide, leave room for a border of 2 pixels on each side
+        let pixel_size = cell.height() / (sprite_height as u32 + 4);
+        let origin_x = cell.center().x() - 2 * pixel_size as i32;
+        let origin_y = cell.center().y() - (sprite_height as i32 * pixel_size as i32) / 2;
+
+        canvas.set_draw_color(color);
+        for (ly, row) in sprite.iter().enumerate() {
+            for lx in 0..4 {
+                if *row & (0x80 >> lx) == 0 {
+                    continue;
+                }
+
+                canvas.fill_rect(Rect::new(
+                    origin_x + lx * pixel_size as i32,
+                    origin_y + ly as i32 * pixel_size as i32,
+                    pixel_size,
+                    pixel_size,
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn key_at(&self, x: i32, y: i32) -> Option<usize> {
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                if self.key_rect(row, col).contains_point((x, y)) {
+                    return Some(*key);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn key_rect(&self, row: usize, col: usize) -> Rect {
+        let cell_size = self.cell_size();
+        let grid_size = cell_size * KEYPAD_LAYOUT.len() as u32;
+        let origin_x = self.area.x() + (self.area.width() - grid_size) as i32 / 2;
+        let origin_y = self.area.y() + (self.area.height() - grid_size) as i32 / 2;
+
+        Rect::new(
+            origin_x + (col as u32 * cell_size) as i32,
+            origin_y + (row as u32 * cell_size) as i32,
+            cell_size,
+            cell_size,
+        )
+    }
+
+    fn cell_size(&self) -> u32 {
+        self.area.width().min(self.area.height()) / KEYPAD_LAYOUT.len() as u32
+    }
+}
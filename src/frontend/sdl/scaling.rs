@@ -0,0 +1,62 @@
+use sdl2::rect::Rect;
+
+use crate::config;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScaleMode {
+    // Largest whole multiple of the display resolution, letterboxed
+    Integer,
+    // Largest size keeping the display aspect ratio, letterboxed
+    Aspect,
+    // Fill the whole area
+    Stretch,
+}
+
+impl ScaleMode {
+    pub const ALL: [ScaleMode; 3] = [ScaleMode::Integer, ScaleMode::Aspect, ScaleMode::Stretch];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScaleMode::Integer => "integer",
+            ScaleMode::Aspect => "aspect",
+            ScaleMode::Stretch => "stretch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| mode.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // Returns where the display is drawn inside the given area
+    pub fn fit(self, area: Rect) -> Rect {
+        let (width, height) = (config::CHIP8_WIDTH, config::CHIP8_HEIGHT);
+
+        let (fit_width, fit_height) = match self {
+            ScaleMode::Integer if area.width() >= width && area.height() >= height => {
+                let scale = (area.width() / width).min(area.height() / height);
+                (width * scale, height * scale)
+            }
+            // Fall back to aspect-correct scaling when the area is smaller than the display
+            ScaleMode::Integer | ScaleMode::Aspect => {
+                if area.width() * height > area.height() * width {
+                    (area.height() * width / height, area.height())
+                } else {
+                    (area.width(), area.width() * height / width)
+                }
+            }
+            ScaleMode::Stretch => (area.width(), area.height()),
+        };
+
+        Rect::new(
+            area.x() + (area.width() - fit_width) as i32 / 2,
+            area.y() + (area.height() - fit_height) as i32 / 2,
+            fit_width.max(1),
+            fit_height.max(1),
+        )
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+
+use crate::chip8::Chip8;
+
+// Matching lines shown before a divergence
+const CONTEXT_LINES: usize = 5;
+
+// One "NAME=HEX" pair of a trace line
+struct Field {
+    name: String,
+    value: u16,
+    // The value as written in the trace
+    text: String,
+}
+
+struct ReferenceLine {
+    number: usize,
+    text: String,
+    fields: Vec<Field>,
+}
+
+// A trace captured from another emulator, one instruction per line as written by
+// `Chip8::trace_line`. Only the fields present in both traces are compared, so the reference can
+// leave some out. Blank lines and lines starting with '#' are skipped
+pub struct ReferenceTrace {
+    lines: Vec<ReferenceLine>,
+}
+
+impl ReferenceTrace {
+    pub fn parse(trace: &str) -> Result<Self, String> {
+        let mut lines = Vec::new();
+
+        for (number, line) in trace.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields =
+                parse_fields(line).map_err(|err| format!("Line {}: {}", number + 1, err))?;
+            lines.push(ReferenceLine {
+                number: number + 1,
+                text: line.to_string(),
+                fields,
+            });
+        }
+
+        Ok(Self { lines })
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+fn parse_fields(line: &str) -> Result<Vec<Field>, String> {
+    line.split_whitespace()
+        .map(|field| {
+            let (name, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("'{}' is not NAME=value", field))?;
+            Ok(Field {
+                name: name.to_ascii_uppercase(),
+                value: u16::from_str_radix(value, 16)
+                    .map_err(|_| format!("'{}' is not a hex value", value))?,
+                text: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+// First instruction where a run differs from the reference trace
+#[derive(Debug)]
+pub struct Divergence {
+    pub instruction: usize,
+    pub reference_line: usize,
+    // Matching reference lines before the divergence
+    context: Vec<String>,
+    expected: String,
+    // None when the program stopped before the end of the reference
+    actual: Option<String>,
+    // Name, expected and actual value of every differing field
+    differences: Vec<(String, String, String)>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.actual.is_some() {
+            write!(
+                f,
+                "Divergence at instruction {} (reference line {}):",
+                self.instruction, self.reference_line
+            )?;
+            for (name, expected, actual) in self.differences.iter() {
+                write!(f, " {}={} expected {}", name, actual, expected)?;
+            }
+            writeln!(f)?;
+        } else {
+            writeln!(
+                f,
+                "The program stopped after {} instructions, the reference continues at line {}",
+                self.instruction - 1,
+                self.reference_line
+            )?;
+        }
+
+        for line in self.context.iter() {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "- {}", self.expected)?;
+        if let Some(actual) = &self.actual {
+            writeln!(f, "+ {}", actual)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Checks a run against a reference trace one instruction at a time. Once the reference ends or
+// a divergence is found, the remaining instructions are ignored
+pub struct TraceComparison {
+    reference: ReferenceTrace,
+    compared: usize,
+    context: VecDeque<String>,
+    divergence: Option<Divergence>,
+}
+
+impl TraceComparison {
+    pub fn new(reference: ReferenceTrace) -> Self {
+        Self {
+            reference,
+            compared: 0,
+            context: VecDeque::with_capacity(CONTEXT_LINES),
+            divergence: None,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.divergence.is_some() || self.compared == self.reference.len()
+    }
+
+    pub fn push(&mut self, chip8: &Chip8) {
+        if self.is_done() {
+            return;
+        }
+
+        let reference = &self.reference.lines[self.compared];
+        self.compared += 1;
+
+        let line = chip8.trace_line();
+        let fields = parse_fields(&line).unwrap();
+        let differences: Vec<_> = reference
+            .fields
+            .iter()
+            .filter_map(|expected| {
+                fields
+                    .iter()
+                    .find(|actual| actual.name == expected.name)
+                    .filter(|actual| actual.value != expected.value)
+                    .map(|actual| {
+                        (
+                            expected.name.clone(),
+                            expected.text.clone(),
+                            actual.text.clone(),
+                        )
+                    })
+            })
+            .collect();
+
+        if differences.is_empty() {
+            if self.context.len() == CONTEXT_LINES {
+                self.context.pop_front();
+            }
+            self.context.push_back(reference.text.clone());
+        } else {
+            self.divergence = Some(Divergence {
+                instruction: self.compared,
+                reference_line: reference.number,
+                context: self.context.drain(..).collect(),
+                expected: reference.text.clone(),
+                actual: Some(line),
+                differences,
+            });
+        }
+    }
+
+    // Number of instructions that matched the reference, or where the run diverged
+    pub fn finish(mut self) -> Result<usize, Divergence> {
+        if let Some(divergence) = self.divergence {
+            return Err(divergence);
+        }
+
+        // The run stopped before the end of the reference
+        if let Some(reference) = self.reference.lines.get(self.compared) {
+            return Err(Divergence {
+                instruction: self.compared + 1,
+                reference_line: reference.number,
+                context: self.context.drain(..).collect(),
+                expected: reference.text.clone(),
+                actual: None,
+                differences: Vec::new(),
+            });
+        }
+
+        Ok(self.compared)
+    }
+}
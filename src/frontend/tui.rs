@@ -0,0 +1,260 @@
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use super::capture::Captures;
+use crate::chip8::audio::Buzzer;
+use crate::chip8::error::Chip8Error;
+use crate::chip8::palette::Theme;
+use crate::chip8::Chip8;
+use crate::config;
+
+const WIDTH: usize = config::CHIP8_WIDTH as usize;
+const HEIGHT: usize = config::CHIP8_HEIGHT as usize;
+
+// Each character cell shows two pixels, the top one in the foreground color
+const HALF_BLOCK: char = '▀';
+
+const BELL: char = '\x07';
+
+const HELP: &str = "Esc quits, F2 changes the theme, F3 toggles persistence";
+
+// Terminal output and keyboard around the interpreter, for machines without a display
+pub struct Tui {
+    chip8: Chip8,
+    key_map: HashMap<KeyCode, usize>,
+    theme: Theme,
+    // The terminal only rings its bell, the buzzer is rendered for audio captures
+    buzzer: Buzzer,
+    captures: Captures,
+
+    // Most terminals only report presses and auto-repeats. Until a release is seen, a key is
+    // released after it hasn't been pressed for a few frames: long enough after the first press
+    // for the auto-repeat to start, shorter once it repeats
+    reports_releases: bool,
+    hold_frames: [u32; config::CHIP8_KEY_COUNT],
+
+    sound_playing: bool,
+    // Revision of the display on the terminal, to skip drawing frames that didn't change
+    drawn_revision: Option<u64>,
+}
+
+impl Tui {
+    pub fn new(chip8: Chip8, buzzer: Buzzer) -> Self {
+        let captures = Captures::new(buzzer.sample_rate());
+
+        Self {
+            chip8,
+            key_map: config::create_tui_key_map(),
+            theme: Theme::Classic,
+            buzzer,
+            captures,
+            reports_releases: false,
+            hold_frames: [0; config::CHIP8_KEY_COUNT],
+            sound_playing: false,
+            drawn_revision: None,
+        }
+    }
+
+    // Theme the theme hotkey cycles from. The interpreter's palette is kept, so colors given on
+    // the command line stay until the theme changes
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn captures_mut(&mut self) -> &mut Captures {
+        &mut self.captures
+    }
+
+    fn next_theme(&mut self) {
+        self.theme = self.theme.next();
+        self.chip8.set_palette(self.theme.palette());
+    }
+
+    // Runs until Esc is pressed or the program fails. The terminal is restored either way
+    pub fn run(&mut self) -> Result<(), Chip8Error> {
+        // Ask for key release events where the terminal supports it
+        let enhanced_keyboard = terminal::supports_keyboard_enhancement().unwrap_or(false);
+
+        terminal::enable_raw_mode()?;
+        let mut terminal = TerminalGuard {
+            enhanced_keyboard: false,
+        };
+
+        // Declared after the guard, so it is flushed before the terminal is restored
+        let mut out = BufWriter::new(io::stdout());
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        if enhanced_keyboard {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+            terminal.enhanced_keyboard = true;
+        }
+
+        let result = self.run_loop(&mut out);
+        self.captures.stop_all();
+
+        result
+    }
+
+    fn run_loop(&mut self, out: &mut BufWriter<Stdout>) -> Result<(), Chip8Error> {
+        let frame_period = Duration::from_secs_f64(config::CHIP8_FRAME_FREQ);
+        let mut next_frame = Instant::now();
+
+        loop {
+            // Wait for the next frame while handling input
+            let now = Instant::now();
+            if now < next_frame {
+                if event::poll(next_frame - now)? {
+                    match event::read()? {
+                        Event::Key(key) if !self.handle_key(key) => return Ok(()),
+                        Event::Resize(..) => {
+                            queue!(out, Clear(ClearType::All))?;
+                            self.drawn_revision = None;
+                        }
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+
+            // Keep the frame rate, unless far behind
+            next_frame += frame_period;
+            if now >= next_frame + frame_period * config::CHIP8_MAX_FRAME_LAG {
+                next_frame = now + frame_period;
+            }
+
+            self.frame(out)?;
+        }
+    }
+
+    // Returns false to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if key.code == KeyCode::Esc || ctrl_c {
+            return false;
+        }
+
+        if key.kind == KeyEventKind::Release {
+            self.reports_releases = true;
+            if let Some(key) = self.key_map.get(&key.code) {
+                self.chip8.key_up(*key);
+                self.hold_frames[*key] = 0;
+            }
+            return true;
+        }
+
+        match key.code {
+            config::TUI_HOTKEY_NEXT_THEME if key.kind == KeyEventKind::Press => self.next_theme(),
+            config::TUI_HOTKEY_TOGGLE_PERSISTENCE if key.kind == KeyEventKind::Press => {
+                let enabled = self.chip8.screen().persistence();
+                self.chip8.set_persistence(!enabled);
+            }
+            code => {
+                if let Some(key) = self.key_map.get(&code) {
+                    self.chip8.key_down(*key);
+                    if !self.reports_releases {
+                        // A press while the key is down is an auto-repeat
+                        self.hold_frames[*key] = if self.hold_frames[*key] > 0 {
+                            config::CHIP8_TUI_KEY_REPEAT_HOLD_FRAMES
+                        } else {
+                            config::CHIP8_TUI_KEY_HOLD_FRAMES
+                        };
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    // Advance emulated time by one frame, then show it
+    fn frame(&mut self, out: &mut BufWriter<Stdout>) -> Result<(), Chip8Error> {
+        for (key, frames) in self.hold_frames.iter_mut().enumerate() {
+            if *frames > 0 {
+                *frames -= 1;
+                if *frames == 0 {
+                    self.chip8.key_up(key);
+                }
+            }
+        }
+
+        let sound_playing = self.chip8.run_frame()?;
+        if sound_playing && !self.sound_playing {
+            queue!(out, Print(BELL))?;
+        }
+        self.sound_playing = sound_playing;
+
+        let samples = self.buzzer.render_frame(sound_playing);
+        self.captures.record_frame(self.chip8.screen(), samples);
+
+        self.draw(out)?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    fn draw(&mut self, out: &mut BufWriter<Stdout>) -> io::Result<()> {
+        let revision = self.chip8.screen().revision();
+        if self.drawn_revision == Some(revision) {
+            return Ok(());
+        }
+
+        let colors = self.chip8.screen().pixel_colors();
+        let color = |x: usize, y: usize| {
+            let i = (y * WIDTH + x) * 3;
+            Color::Rgb {
+                r: colors[i],
+                g: colors[i + 1],
+                b: colors[i + 2],
+            }
+        };
+
+        let mut current = None;
+        for row in 0..HEIGHT / 2 {
+            queue!(out, MoveTo(0, row as u16))?;
+
+            for x in 0..WIDTH {
+                let cell = (color(x, row * 2), color(x, row * 2 + 1));
+                if current != Some(cell) {
+                    queue!(out, SetForegroundColor(cell.0), SetBackgroundColor(cell.1))?;
+                    current = Some(cell);
+                }
+                queue!(out, Print(HALF_BLOCK))?;
+            }
+        }
+        queue!(out, ResetColor, MoveTo(0, (HEIGHT / 2) as u16), Print(HELP))?;
+
+        self.drawn_revision = Some(revision);
+
+        Ok(())
+    }
+}
+
+// Restores the terminal when dropped, including when the frontend fails half way through
+// setting it up
+struct TerminalGuard {
+    enhanced_keyboard: bool,
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // Nothing more can be done if restoring fails
+        let mut out = io::stdout();
+        if self.enhanced_keyboard {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
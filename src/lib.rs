@@ -0,0 +1,3 @@
+pub mod chip8;
+pub mod config;
+pub mod frontend;
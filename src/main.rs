@@ -1,12 +1,38 @@
-use clap::{App, Arg};
-use sdl2::audio::AudioSpecDesired;
-use std::fs::File;
-use std::io::{Error, Read};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{BufWriter, Error, Read, Write};
+use std::path::Path;
+use std::process;
 
-use chip8::Chip8;
+use chip8oxyde::chip8::audio::{Buzzer, Waveform};
+use chip8oxyde::chip8::engine::Engine;
+use chip8oxyde::chip8::palette::{Palette, Theme};
+use chip8oxyde::chip8::quirks::QuirkProfile;
+use chip8oxyde::chip8::screen::Screen;
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::config;
+#[cfg(feature = "images")]
+use chip8oxyde::frontend::capture;
+use chip8oxyde::frontend::headless::{Headless, KeyScript};
+use chip8oxyde::frontend::trace::{ReferenceTrace, TraceComparison};
 
-mod chip8;
-mod config;
+#[cfg(feature = "sdl")]
+use chip8oxyde::frontend::capture::recorder::RecordingFormat;
+#[cfg(feature = "sdl")]
+use chip8oxyde::frontend::sdl::audio::{AudioStream, SampleQueue};
+#[cfg(feature = "sdl")]
+use chip8oxyde::frontend::sdl::crt::CrtEffects;
+#[cfg(feature = "sdl")]
+use chip8oxyde::frontend::sdl::scaling::ScaleMode;
+#[cfg(feature = "sdl")]
+use chip8oxyde::frontend::sdl::Sdl;
+#[cfg(feature = "tui")]
+use chip8oxyde::frontend::tui::Tui;
+#[cfg(feature = "sdl")]
+use sdl2::audio::AudioSpecDesired;
+#[cfg(any(feature = "sdl", feature = "tui"))]
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn read_file(file_path: &str) -> Result<Vec<u8>, Error> {
     let mut file = File::open(file_path)?;
@@ -18,54 +44,534 @@ fn read_file(file_path: &str) -> Result<Vec<u8>, Error> {
     Ok(buffer)
 }
 
-fn main() {
-    let matches = App::new("Chip8Oxyde")
-        .author("Mikastiv <m.leblanc_3@hotmail.com>")
-        .about("Chip8 emulator written in Rust")
-        .version("0.1.0")
-        .arg(
-            Arg::with_name("Program file")
-                .help("Program to load")
-                .index(1)
-                .required(true),
-        )
-        .get_matches();
+fn fail(message: impl Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn validate_color(s: String) -> Result<(), String> {
+    match Palette::parse_color(&s) {
+        Some(_) => Ok(()),
+        None => Err(format!("'{}' is not a RRGGBB color", s)),
+    }
+}
+
+fn validate_scale(s: String) -> Result<(), String> {
+    match s.parse::<u32>() {
+        Ok(scale) if scale > 0 && scale <= config::CHIP8_CAPTURE_MAX_SCALE => Ok(()),
+        _ => Err(format!(
+            "'{}' is not an integer between 1 and {}",
+            s,
+            config::CHIP8_CAPTURE_MAX_SCALE
+        )),
+    }
+}
+
+fn validate_count(s: String) -> Result<(), String> {
+    match s.parse::<u64>() {
+        Ok(_) => Ok(()),
+        _ => Err(format!("'{}' is not a non-negative integer", s)),
+    }
+}
+
+fn validate_range(s: &str, min: f32, max: f32) -> Result<(), String> {
+    match s.parse::<f32>() {
+        Ok(value) if value >= min && value <= max => Ok(()),
+        _ => Err(format!(
+            "'{}' is not a number between {} and {}",
+            s, min, max
+        )),
+    }
+}
+
+// Arguments shared by every way of running a program
+fn program_args<'a>(frequency: &'a str, volume: &'a str) -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name("Program file")
+            .help("Program to load")
+            .index(1)
+            .required(true),
+        Arg::with_name("theme")
+            .long("theme")
+            .takes_value(true)
+            .possible_values(&Theme::ALL.iter().map(|t| t.name()).collect::<Vec<_>>())
+            .default_value(Theme::Classic.name())
+            .help("Display color theme, cycle at runtime with F2"),
+        Arg::with_name("foreground")
+            .long("fg")
+            .takes_value(true)
+            .validator(validate_color)
+            .help("Foreground color as RRGGBB, overrides the theme"),
+        Arg::with_name("background")
+            .long("bg")
+            .takes_value(true)
+            .validator(validate_color)
+            .help("Background color as RRGGBB, overrides the theme"),
+        Arg::with_name("persistence")
+            .long("persistence")
+            .help("Fades erased pixels out over a few frames to reduce flicker, toggle with F3"),
+        Arg::with_name("capture-scale")
+            .long("capture-scale")
+            .takes_value(true)
+            .default_value("1")
+            .validator(validate_scale)
+            .help("Scale of the screenshots taken with F12 and the recordings taken with F8"),
+        Arg::with_name("waveform")
+            .long("waveform")
+            .takes_value(true)
+            .default_value("square")
+            .validator(|s| Waveform::parse(&s).map(|_| ()))
+            .help("Buzzer waveform: square, square:<duty>, triangle, sine or noise"),
+        Arg::with_name("frequency")
+            .long("frequency")
+            .takes_value(true)
+            .default_value(frequency)
+            .validator(|s| validate_range(&s, 20.0, 20000.0))
+            .help("Buzzer frequency in Hz"),
+        Arg::with_name("volume")
+            .long("volume")
+            .takes_value(true)
+            .default_value(volume)
+            .validator(|s| validate_range(&s, 0.0, 1.0))
+            .help("Buzzer volume between 0 and 1. F9 mutes, F10 and F11 change the volume"),
+        Arg::with_name("wav")
+            .long("wav")
+            .takes_value(true)
+            .value_name("FILE")
+            .help("Records the buzzer to a WAV file"),
+        Arg::with_name("quirks")
+            .long("quirks")
+            .takes_value(true)
+            .possible_values(
+                &QuirkProfile::ALL
+                    .iter()
+                    .map(|p| p.name())
+                    .collect::<Vec<_>>(),
+            )
+            .default_value(QuirkProfile::Modern.name())
+            .help("Interpreter behaviours expected by the program"),
+        Arg::with_name("engine")
+            .long("engine")
+            .takes_value(true)
+            .possible_values(&Engine::ALL.iter().map(|e| e.name()).collect::<Vec<_>>())
+            .default_value(Engine::Interpreter.name())
+            .help(
+                "How instructions are executed: one at a time, in basic blocks, or in blocks \
+                 checked against the interpreter",
+            ),
+        Arg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .validator(validate_count)
+            .help("Seed of the random number generator, runs with the same seed are identical"),
+    ]
+}
+
+// Arguments only used by the SDL window
+#[cfg(feature = "sdl")]
+fn display_args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name("crt")
+            .long("crt")
+            .takes_value(true)
+            .validator(|s| CrtEffects::parse(&s).map(|_| ()))
+            .help(
+                "CRT effects to apply: a comma separated list of scanlines, grid, bloom, \
+                 curvature or all. Toggle with F4",
+            ),
+        Arg::with_name("scale-mode")
+            .long("scale-mode")
+            .takes_value(true)
+            .possible_values(&ScaleMode::ALL.iter().map(|m| m.name()).collect::<Vec<_>>())
+            .default_value(ScaleMode::Integer.name())
+            .help("How the display fills the window, cycle at runtime with F5"),
+        Arg::with_name("record-format")
+            .long("record-format")
+            .takes_value(true)
+            .possible_values(
+                &RecordingFormat::ALL
+                    .iter()
+                    .map(|f| f.name())
+                    .collect::<Vec<_>>(),
+            )
+            .default_value(RecordingFormat::Gif.name())
+            .help("Format of the recordings taken with F8: animated GIF or numbered PNG files"),
+        Arg::with_name("keypad")
+            .long("keypad")
+            .help("Shows a clickable hex keypad next to the display"),
+    ]
+}
 
+#[cfg(not(feature = "sdl"))]
+fn display_args<'a>() -> Vec<Arg<'a, 'a>> {
+    Vec::new()
+}
+
+// Arguments of the terminal frontend
+#[cfg(feature = "tui")]
+fn terminal_args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![Arg::with_name("terminal")
+        .long("terminal")
+        .conflicts_with("headless")
+        // Screenshots, recordings and the window settings are only available in the window
+        .conflicts_with_all(&[
+            "capture-scale",
+            "crt",
+            "scale-mode",
+            "record-format",
+            "keypad",
+        ])
+        .help("Runs in the terminal, e.g. over SSH on a machine without a display")]
+}
+
+#[cfg(not(feature = "tui"))]
+fn terminal_args<'a>() -> Vec<Arg<'a, 'a>> {
+    Vec::new()
+}
+
+// Arguments of the runner without a display
+fn headless_args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name("headless")
+            .long("headless")
+            .help("Runs without a window or an audio device, then prints the machine state"),
+        frames_arg(),
+        keys_arg().requires("headless"),
+        Arg::with_name("png")
+            .long("png")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("headless")
+            .help("Saves the final display to a PNG file, at the capture scale"),
+        Arg::with_name("ascii")
+            .long("ascii")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("headless")
+            .help("Saves the final display as text, '-' prints it"),
+        Arg::with_name("trace")
+            .long("trace")
+            .takes_value(true)
+            .value_name("FILE")
+            .requires("headless")
+            .help("Writes the machine state before every executed instruction to a file"),
+    ]
+}
+
+// Arguments of the comparison with a trace from another emulator
+fn compare_trace_args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name("Reference trace")
+            .help("Trace to compare with, one instruction per line")
+            .index(2)
+            .required(true),
+        frames_arg(),
+        keys_arg(),
+    ]
+}
+
+fn frames_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("frames")
+        .long("frames")
+        .takes_value(true)
+        .default_value("600")
+        .validator(validate_count)
+        .help("Number of 60 Hz frames to run")
+}
+
+fn keys_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("keys")
+        .long("keys")
+        .takes_value(true)
+        .value_name("FILE")
+        .help("Key presses to apply, one '<frame> <key> down|up' per line")
+}
+
+// Load the program and apply the display settings common to every frontend
+fn create_chip8(matches: &ArgMatches, default_seed: u64) -> Chip8 {
     let program_file = matches.value_of("Program file").unwrap();
-    let program_buffer = read_file(program_file).unwrap();
+    let program_buffer = read_file(program_file)
+        .unwrap_or_else(|err| fail(format!("Could not read {}: {}", program_file, err)));
+
+    let mut palette = Theme::from_name(matches.value_of("theme").unwrap())
+        .unwrap()
+        .palette();
+    if let Some(color) = matches.value_of("foreground") {
+        palette = palette.with_foreground(Palette::parse_color(color).unwrap());
+    }
+    if let Some(color) = matches.value_of("background") {
+        palette = palette.with_background(Palette::parse_color(color).unwrap());
+    }
+
+    let mut chip8 = Chip8::new();
+    chip8.seed(
+        matches
+            .value_of("seed")
+            .map_or(default_seed, |seed| seed.parse().unwrap()),
+    );
+    chip8.set_quirks(
+        QuirkProfile::from_name(matches.value_of("quirks").unwrap())
+            .unwrap()
+            .quirks(),
+    );
+    chip8.set_engine(Engine::from_name(matches.value_of("engine").unwrap()).unwrap());
+    chip8.set_palette(palette);
+    chip8.set_persistence(matches.is_present("persistence"));
+    chip8.load(&program_buffer).unwrap_or_else(|err| fail(err));
+
+    chip8
+}
+
+fn create_buzzer(matches: &ArgMatches, sample_rate: u32) -> Buzzer {
+    let waveform = Waveform::parse(matches.value_of("waveform").unwrap()).unwrap();
+    let frequency = matches.value_of("frequency").unwrap().parse().unwrap();
+    let volume = matches.value_of("volume").unwrap().parse().unwrap();
+
+    Buzzer::new(waveform, frequency, volume, sample_rate)
+}
+
+fn read_key_script(matches: &ArgMatches) -> KeyScript {
+    match matches.value_of("keys") {
+        Some(path) => {
+            let script = fs::read_to_string(path)
+                .unwrap_or_else(|err| fail(format!("Could not read {}: {}", path, err)));
+            KeyScript::parse(&script).unwrap_or_else(|err| fail(format!("{}: {}", path, err)))
+        }
+        None => KeyScript::default(),
+    }
+}
+
+fn create_headless(matches: &ArgMatches) -> Headless {
+    // Headless runs are reproducible unless asked otherwise
+    let chip8 = create_chip8(matches, 0);
+    let buzzer = create_buzzer(matches, config::CHIP8_SOUND_SAMPLE_RATE as u32);
+
+    Headless::new(chip8, buzzer, read_key_script(matches))
+}
+
+fn run_headless(matches: &ArgMatches) {
+    let mut headless = create_headless(matches);
+    if let Some(path) = matches.value_of("wav") {
+        headless
+            .captures_mut()
+            .start_audio_capture(Path::new(path))
+            .unwrap_or_else(|err| fail(err));
+    }
+
+    // The state is still printed when the program fails, to help finding out why
+    let frames = matches.value_of("frames").unwrap().parse().unwrap();
+    let result = match matches.value_of("trace") {
+        Some(path) => {
+            let file = File::create(path)
+                .unwrap_or_else(|err| fail(format!("Could not create {}: {}", path, err)));
+            let mut writer = BufWriter::new(file);
+            headless
+                .run_traced(frames, &mut |chip8| {
+                    Ok(writeln!(writer, "{}", chip8.trace_line())?)
+                })
+                .and_then(|_| Ok(writer.flush()?))
+        }
+        None => headless.run(frames),
+    };
+
+    print!("{}", headless.report());
+
+    let screen = headless.chip8().screen();
+    if let Some(path) = matches.value_of("png") {
+        let scale = matches.value_of("capture-scale").unwrap().parse().unwrap();
+        save_png(screen, Path::new(path), scale);
+    }
+    match matches.value_of("ascii") {
+        Some("-") => print!("{}", screen.to_ascii()),
+        Some(path) => fs::write(path, screen.to_ascii())
+            .unwrap_or_else(|err| fail(format!("Could not write {}: {}", path, err))),
+        None => {}
+    }
+
+    if let Err(err) = result {
+        fail(format!("The program stopped: {}", err));
+    }
+}
+
+#[cfg(feature = "images")]
+fn save_png(screen: &Screen, path: &Path, scale: u32) {
+    capture::save_screenshot(screen, path, scale).unwrap_or_else(|err| fail(err));
+}
+
+#[cfg(not(feature = "images"))]
+fn save_png(_screen: &Screen, _path: &Path, _scale: u32) {
+    fail("Built without the images feature, --png is not available");
+}
+
+fn compare_trace(matches: &ArgMatches) {
+    let path = matches.value_of("Reference trace").unwrap();
+    let trace = fs::read_to_string(path)
+        .unwrap_or_else(|err| fail(format!("Could not read {}: {}", path, err)));
+    let reference =
+        ReferenceTrace::parse(&trace).unwrap_or_else(|err| fail(format!("{}: {}", path, err)));
+    if reference.is_empty() {
+        fail(format!("{} has no instructions", path));
+    }
+
+    let mut headless = create_headless(matches);
+    let mut comparison = TraceComparison::new(reference);
+    let result = headless.run_traced(
+        matches.value_of("frames").unwrap().parse().unwrap(),
+        &mut |chip8| {
+            comparison.push(chip8);
+            Ok(())
+        },
+    );
+
+    match comparison.finish() {
+        Ok(count) => println!("The {} instructions of the reference trace match", count),
+        Err(divergence) => {
+            print!("{}", divergence);
+            if let Err(err) = result {
+                println!("The program stopped: {}", err);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+// Seed of interactive runs, which differ every time
+#[cfg(any(feature = "sdl", feature = "tui"))]
+fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(matches: &ArgMatches) {
+    let chip8 = create_chip8(matches, time_seed());
+    let buzzer = create_buzzer(matches, config::CHIP8_SOUND_SAMPLE_RATE as u32);
+
+    let mut tui = Tui::new(chip8, buzzer);
+    tui.set_theme(Theme::from_name(matches.value_of("theme").unwrap()).unwrap());
+    if let Some(path) = matches.value_of("wav") {
+        tui.captures_mut()
+            .start_audio_capture(Path::new(path))
+            .unwrap_or_else(|err| fail(err));
+    }
+
+    if let Err(err) = tui.run() {
+        fail(format!("The program stopped: {}", err));
+    }
+}
+
+#[cfg(feature = "sdl")]
+fn run_sdl(matches: &ArgMatches) {
+    let chip8 = create_chip8(matches, time_seed());
+    let show_keypad = matches.is_present("keypad");
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let audio_subsystem = sdl_context.audio().unwrap();
 
-    let window = video_subsystem
+    let mut window_width = config::CHIP8_WIDTH * config::CHIP8_WINDOW_SCALE_FACTOR;
+    if show_keypad {
+        window_width += config::CHIP8_KEYPAD_WIDTH;
+    }
+
+    let mut window = video_subsystem
         .window(
             config::WINDOW_TITLE,
-            config::CHIP8_WIDTH * config::CHIP8_WINDOW_SCALE_FACTOR,
+            window_width,
             config::CHIP8_HEIGHT * config::CHIP8_WINDOW_SCALE_FACTOR,
         )
         .position_centered()
+        .resizable()
         .build()
         .unwrap();
+    window
+        .set_minimum_size(config::CHIP8_WIDTH, config::CHIP8_HEIGHT)
+        .unwrap();
 
     let canvas = window.into_canvas().build().unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
 
     let audio_spec = AudioSpecDesired {
-        freq: Some(44100),
+        freq: Some(config::CHIP8_SOUND_SAMPLE_RATE),
         channels: Some(1),
         samples: None,
     };
 
     let audio_device = audio_subsystem
-        .open_playback(None, &audio_spec, |spec| chip8::audio::SquareWave {
-            phase_inc: config::CHIP8_SOUND_NOTE_FREQ / spec.freq as f32,
-            phase: 0.0,
-            volume: 0.05,
+        .open_playback(None, &audio_spec, |spec| {
+            let capacity = (spec.freq as f64 * config::CHIP8_SOUND_MAX_LATENCY) as usize;
+            AudioStream::new(SampleQueue::new(capacity))
         })
         .unwrap();
 
-    let mut chip8 = Chip8::new(canvas, audio_device);
-    chip8.load(&program_buffer).unwrap();
-    chip8.run(&mut event_pump);
+    let buzzer = create_buzzer(matches, audio_device.spec().freq as u32);
+
+    let mut sdl = Sdl::new(chip8, canvas, audio_device, buzzer);
+    sdl.captures_mut().set_recording_format(
+        RecordingFormat::from_name(matches.value_of("record-format").unwrap()).unwrap(),
+    );
+    sdl.captures_mut()
+        .set_capture_scale(matches.value_of("capture-scale").unwrap().parse().unwrap());
+    sdl.set_scale_mode(ScaleMode::from_name(matches.value_of("scale-mode").unwrap()).unwrap());
+    sdl.set_theme(Theme::from_name(matches.value_of("theme").unwrap()).unwrap());
+    sdl.set_crt_filter(
+        matches
+            .value_of("crt")
+            .map(|effects| CrtEffects::parse(effects).unwrap()),
+    );
+    if show_keypad {
+        sdl.show_keypad();
+    }
+    if let Some(path) = matches.value_of("wav") {
+        sdl.captures_mut()
+            .start_audio_capture(Path::new(path))
+            .unwrap();
+    }
+    sdl.run(&mut event_pump);
+}
+
+#[cfg(not(feature = "sdl"))]
+fn run_sdl(_matches: &ArgMatches) {
+    fail("Built without the sdl feature, only `run --headless` is available");
+}
+
+fn main() {
+    let frequency = config::CHIP8_SOUND_NOTE_FREQ.to_string();
+    let volume = config::CHIP8_SOUND_VOLUME.to_string();
+
+    let matches = App::new("Chip8Oxyde")
+        .author("Mikastiv <m.leblanc_3@hotmail.com>")
+        .about("Chip8 emulator written in Rust")
+        .version("0.1.0")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .args(&program_args(&frequency, &volume))
+        .args(&display_args())
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Runs a program, with --headless or --terminal it runs without a window")
+                .args(&program_args(&frequency, &volume))
+                .args(&display_args())
+                .args(&headless_args())
+                .args(&terminal_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("compare-trace")
+                .about("Runs a program without a display and compares it with a reference trace")
+                .args(&program_args(&frequency, &volume))
+                .args(&compare_trace_args()),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("run", Some(run_matches)) if run_matches.is_present("headless") => {
+            run_headless(run_matches)
+        }
+        #[cfg(feature = "tui")]
+        ("run", Some(run_matches)) if run_matches.is_present("terminal") => run_tui(run_matches),
+        ("run", Some(run_matches)) => run_sdl(run_matches),
+        ("compare-trace", Some(compare_matches)) => compare_trace(compare_matches),
+        _ => run_sdl(&matches),
+    }
 }
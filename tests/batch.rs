@@ -0,0 +1,74 @@
+#![cfg(feature = "batch")]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chip8oxyde::chip8::quirks::QuirkProfile;
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::frontend::batch::MachineBatch;
+
+const PIXEL_COUNT: usize = 64 * 32;
+
+// Draws a 0 at random positions forever
+const RANDOM_DRAW: [u8; 10] = [0xC0, 0x3F, 0xC1, 0x1F, 0xA0, 0x00, 0xD0, 0x15, 0x12, 0x00];
+
+#[test]
+fn lockstep_matches_single_machines() {
+    let mut batch = MachineBatch::new(
+        Arc::from(&RANDOM_DRAW[..]),
+        QuirkProfile::Modern.quirks(),
+        16,
+    )
+    .unwrap();
+    batch.reset(100);
+    batch.hold_keys(3, &[0x5]);
+    batch.run_frames(30);
+
+    let framebuffers = batch.framebuffers();
+    assert_eq!(framebuffers.len(), 16 * PIXEL_COUNT);
+
+    for (index, framebuffer) in framebuffers.chunks(PIXEL_COUNT).enumerate() {
+        let mut chip8 = Chip8::new();
+        chip8.seed(100 + index as u64);
+        chip8.load(&RANDOM_DRAW).unwrap();
+        for _ in 0..30 {
+            chip8.run_frame().unwrap();
+        }
+
+        let states: Vec<u8> = chip8
+            .screen()
+            .pixel_states()
+            .iter()
+            .map(|state| *state as u8)
+            .collect();
+        assert_eq!(framebuffer, &states[..], "machine {}", index);
+        assert_eq!(batch.chip8(index).registers.v, chip8.registers.v);
+    }
+
+    // Different seeds draw different displays
+    assert_ne!(
+        framebuffers[..PIXEL_COUNT],
+        framebuffers[PIXEL_COUNT..2 * PIXEL_COUNT]
+    );
+    assert!(batch.chip8(3).key_states()[0x5]);
+}
+
+#[test]
+fn failed_machines_stop() {
+    let program =
+        fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/flags.ch8")).unwrap();
+    let mut batch =
+        MachineBatch::new(Arc::from(program), QuirkProfile::Modern.quirks(), 4).unwrap();
+    batch.run_frames(10);
+    assert!((0..batch.len()).all(|index| batch.error(index).is_none()));
+
+    // A return outside of a subroutine
+    let mut batch = MachineBatch::new(Arc::from(&[0x00, 0xEE][..]), Default::default(), 2).unwrap();
+    batch.run_frames(2);
+    assert!(batch.error(1).is_some());
+    batch.reset(0);
+    assert!(batch.error(1).is_none());
+
+    assert!(MachineBatch::new(Arc::from(vec![0; 0x1000]), Default::default(), 2).is_err());
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::frontend::environment::{read_bcd, Environment, Game};
+
+fn keypad_game() -> Game {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/keypad.ch8");
+
+    // The keypad ROM moves V7 down a row of 6 pixels for every key it has checked
+    Game::new(
+        fs::read(path).unwrap(),
+        Box::new(|chip8: &Chip8| (chip8.registers.v[0x7] / 6) as f32),
+        Box::new(|chip8: &Chip8| chip8.registers.v[0x7] >= 12),
+    )
+}
+
+// Holds the keys for the given number of steps, returns the total reward
+fn hold(environment: &mut Environment, keys: &[usize], steps: usize) -> (f32, bool) {
+    let mut total = 0.0;
+    let mut done = false;
+    for _ in 0..steps {
+        let (observation, reward, step_done) = environment.step(keys);
+        assert_eq!(observation.len(), 64 * 32);
+        total += reward;
+        done = step_done;
+    }
+
+    (total, done)
+}
+
+#[test]
+fn episode() {
+    let mut environment = Environment::new(keypad_game());
+    environment.set_frames_per_step(2);
+
+    let observation = environment.reset(1);
+    assert!(observation.iter().all(|pixel| !pixel));
+
+    assert_eq!(hold(&mut environment, &[], 5), (0.0, false));
+    assert_eq!(hold(&mut environment, &[0x1], 5), (0.0, false));
+    assert_eq!(hold(&mut environment, &[], 5), (1.0, false));
+    assert!(environment.chip8().screen().pixel_states().contains(&true));
+
+    assert_eq!(hold(&mut environment, &[0xA, 0x3], 5), (0.0, false));
+    assert_eq!(hold(&mut environment, &[], 5), (1.0, true));
+    assert!(environment.step(&[]).2);
+
+    // A new episode starts over
+    environment.reset(2);
+    assert_eq!(hold(&mut environment, &[], 5), (0.0, false));
+}
+
+#[test]
+fn program_errors_end_the_episode() {
+    let game = Game::new(vec![0x00, 0xEE], Box::new(|_| 0.0), Box::new(|_| false));
+    let mut environment = Environment::new(game);
+
+    assert!(environment.step(&[]).2);
+}
+
+#[test]
+fn bcd_scores() {
+    let mut chip8 = Chip8::new();
+    chip8.memory[0x300..0x303].copy_from_slice(&[1, 2, 7]);
+
+    assert_eq!(read_bcd(&chip8, 0x300, 3), 127);
+    assert_eq!(read_bcd(&chip8, 0x301, 2), 27);
+}
@@ -0,0 +1,91 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use chip8oxyde::chip8::audio::{Buzzer, Waveform};
+use chip8oxyde::chip8::engine::Engine;
+use chip8oxyde::chip8::quirks::QuirkProfile;
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::config;
+use chip8oxyde::frontend::headless::{Headless, KeyScript};
+
+// Enough for every test ROM to reach its final display
+const FRAMES: u64 = 120;
+
+fn test_path(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path)
+}
+
+fn run_rom(rom: &str, profile: QuirkProfile, engine: Engine) -> String {
+    let program = fs::read(test_path(&format!("roms/{}.ch8", rom))).unwrap();
+    let keys = match fs::read_to_string(test_path(&format!("roms/{}.keys", rom))) {
+        Ok(script) => KeyScript::parse(&script).unwrap(),
+        Err(_) => KeyScript::default(),
+    };
+
+    let mut chip8 = Chip8::new();
+    chip8.set_quirks(profile.quirks());
+    chip8.set_engine(engine);
+    chip8.load(&program).unwrap();
+
+    let buzzer = Buzzer::new(
+        Waveform::Square { duty: 0.5 },
+        config::CHIP8_SOUND_NOTE_FREQ,
+        0.0,
+        config::CHIP8_SOUND_SAMPLE_RATE as u32,
+    );
+    let mut headless = Headless::new(chip8, buzzer, keys);
+    headless.run(FRAMES).unwrap();
+
+    headless.chip8().screen().to_ascii()
+}
+
+// Compares the final display with tests/golden/<rom>-<profile>.txt for every quirk profile and
+// engine. Run with UPDATE_GOLDEN=1 to write the golden files instead
+fn check_golden(rom: &str) {
+    for profile in QuirkProfile::ALL.iter() {
+        let golden_path = test_path(&format!("golden/{}-{}.txt", rom, profile.name()));
+
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            let display = run_rom(rom, *profile, Engine::Interpreter);
+            fs::write(&golden_path, &display).unwrap();
+            continue;
+        }
+
+        let golden = fs::read_to_string(&golden_path).unwrap();
+        for engine in Engine::ALL.iter() {
+            let display = run_rom(rom, *profile, *engine);
+            assert!(
+                display == golden,
+                "{} with the {} quirks and the {} engine differs from {}:\n{}",
+                rom,
+                profile.name(),
+                engine.name(),
+                golden_path.display(),
+                display
+            );
+        }
+    }
+}
+
+#[test]
+fn opcodes() {
+    check_golden("opcodes");
+}
+
+#[test]
+fn flags() {
+    check_golden("flags");
+}
+
+#[test]
+fn quirks() {
+    check_golden("quirks");
+}
+
+#[test]
+fn keypad() {
+    check_golden("keypad");
+}
@@ -0,0 +1,175 @@
+; Checks the result and VF of the arithmetic instructions, one mark per check.
+; Each check copies the result to V0 and VF to V1
+        ld v6, 0
+        ld v7, 0
+
+; 8xy4 with carry
+        ld v0, 0xFF
+        ld v2, 0x02
+        add v0, v2
+        ld v1, vf
+        ld v3, 0x01
+        ld v4, 0x01
+        call check
+
+; 8xy4 without carry
+        ld v0, 0x01
+        ld v2, 0x02
+        add v0, v2
+        ld v1, vf
+        ld v3, 0x03
+        ld v4, 0x00
+        call check
+
+; 8xy5 without borrow
+        ld v0, 0x05
+        ld v2, 0x03
+        sub v0, v2
+        ld v1, vf
+        ld v3, 0x02
+        ld v4, 0x01
+        call check
+
+; 8xy5 with borrow
+        ld v0, 0x03
+        ld v2, 0x05
+        sub v0, v2
+        ld v1, vf
+        ld v3, 0xFE
+        ld v4, 0x00
+        call check
+
+; 8xy5 with equal operands does not borrow
+        ld v0, 0x05
+        ld v2, 0x05
+        sub v0, v2
+        ld v1, vf
+        ld v3, 0x00
+        ld v4, 0x01
+        call check
+
+; 8xy7 without borrow
+        ld v0, 0x03
+        ld v2, 0x05
+        subn v0, v2
+        ld v1, vf
+        ld v3, 0x02
+        ld v4, 0x01
+        call check
+
+; 8xy7 with borrow
+        ld v0, 0x05
+        ld v2, 0x03
+        subn v0, v2
+        ld v1, vf
+        ld v3, 0xFE
+        ld v4, 0x00
+        call check
+
+; 8xy7 with equal operands does not borrow
+        ld v0, 0x05
+        ld v2, 0x05
+        subn v0, v2
+        ld v1, vf
+        ld v3, 0x00
+        ld v4, 0x01
+        call check
+
+; 8xy6 shifts out the lowest bit, Vy holds the same value for the shift quirk
+        ld v0, 0x03
+        ld v2, 0x03
+        shr v0, v2
+        ld v1, vf
+        ld v3, 0x01
+        ld v4, 0x01
+        call check
+
+; 8xyE shifts out the highest bit as 1
+        ld v0, 0x81
+        ld v2, 0x81
+        shl v0, v2
+        ld v1, vf
+        ld v3, 0x02
+        ld v4, 0x01
+        call check
+
+; 8xyE without the highest bit
+        ld v0, 0x41
+        ld v2, 0x41
+        shl v0, v2
+        ld v1, vf
+        ld v3, 0x82
+        ld v4, 0x00
+        call check
+
+; VF as the destination of 8xy4 holds the carry
+        ld vf, 0xFF
+        ld v2, 0x02
+        add vf, v2
+        ld v0, vf
+        ld v1, vf
+        ld v3, 0x01
+        ld v4, 0x01
+        call check
+
+; VF as the destination of 8xy5 holds the flag
+        ld vf, 0x05
+        ld v2, 0x03
+        sub vf, v2
+        ld v0, vf
+        ld v1, vf
+        ld v3, 0x01
+        ld v4, 0x01
+        call check
+
+; VF as the destination of 8xy6 holds the flag
+        ld vf, 0x03
+        ld v2, 0x03
+        shr vf, v2
+        ld v0, vf
+        ld v1, vf
+        ld v3, 0x01
+        ld v4, 0x01
+        call check
+
+; VF as the source of 8xy4
+        ld v0, 0xFF
+        ld vf, 0x01
+        add v0, vf
+        ld v1, vf
+        ld v3, 0x00
+        ld v4, 0x01
+        call check
+
+; VF as the source of 8xy5
+        ld v0, 0x01
+        ld vf, 0x02
+        sub v0, vf
+        ld v1, vf
+        ld v3, 0xFF
+        ld v4, 0x00
+        call check
+
+done:   jp done
+
+; Marks a pass when V0 and V1 match the expected values in V3 and V4
+check:  ld i, fail
+        se v0, v3
+        jp mark
+        se v1, v4
+        jp mark
+        ld i, pass
+        jp mark
+; Draws the sprite at I as the next result mark, 8 marks per row
+mark:   drw v6, v7, 4
+        add v6, 8
+        sne v6, 64
+        jp mark_nl
+        ret
+mark_nl: ld v6, 0
+        add v7, 6
+        ret
+
+; Filled square for a passed check, cross for a failed one
+pass:   db 0xF0 0xF0 0xF0 0xF0
+fail:   db 0x90 0x60 0x60 0x90
@@ -0,0 +1,35 @@
+; Waits for a key, then draws its digit followed by a filled square if Ex9E sees it held
+; and a filled square if ExA1 sees it released, then waits for the next key on the next row
+        ld v6, 0
+        ld v7, 0
+
+loop:   ld v2, k
+        ld f, v2
+        drw v6, v7, 5
+        add v6, 8
+        ld i, pass
+        skp v2
+        ld i, fail
+        call mark
+wait:   sknp v2
+        jp wait
+        ld i, pass
+        sknp v2
+        ld i, fail
+        call mark
+        ld v6, 0
+        add v7, 6
+        jp loop
+; Draws the sprite at I as the next result mark, 8 marks per row
+mark:   drw v6, v7, 4
+        add v6, 8
+        sne v6, 64
+        jp mark_nl
+        ret
+mark_nl: ld v6, 0
+        add v7, 6
+        ret
+
+; Filled square for a passed check, cross for a failed one
+pass:   db 0xF0 0xF0 0xF0 0xF0
+fail:   db 0x90 0x60 0x60 0x90
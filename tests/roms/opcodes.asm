@@ -0,0 +1,226 @@
+; Checks the result of every non-flag instruction, one mark per check
+        ld v6, 0
+        ld v7, 0
+
+; 3xkk skips when equal
+        ld v0, 0x42
+        ld i, fail
+        se v0, 0x42
+        jp t1
+        ld i, pass
+t1:     call mark
+
+; 4xkk skips when not equal
+        ld i, fail
+        sne v0, 0x41
+        jp t2
+        ld i, pass
+t2:     call mark
+
+; 5xy0 skips when equal
+        ld v1, 0x42
+        ld i, fail
+        se v0, v1
+        jp t3
+        ld i, pass
+t3:     call mark
+
+; 9xy0 skips when not equal
+        ld v1, 0x43
+        ld i, fail
+        sne v0, v1
+        jp t4
+        ld i, pass
+t4:     call mark
+
+; 7xkk wraps around without touching VF
+        ld v0, 0xFF
+        ld vf, 0x55
+        add v0, 2
+        ld i, fail
+        se v0, 1
+        jp t5
+        se vf, 0x55
+        jp t5
+        ld i, pass
+t5:     call mark
+
+; 8xy0 copies Vy
+        ld v1, 0x37
+        ld v0, v1
+        ld i, pass
+        se v0, 0x37
+        ld i, fail
+        call mark
+
+; 8xy1 OR
+        ld v0, 0x0C
+        ld v1, 0x0A
+        or v0, v1
+        ld i, pass
+        se v0, 0x0E
+        ld i, fail
+        call mark
+
+; 8xy2 AND
+        ld v0, 0x0C
+        ld v1, 0x0A
+        and v0, v1
+        ld i, pass
+        se v0, 0x08
+        ld i, fail
+        call mark
+
+; 8xy3 XOR
+        ld v0, 0x0C
+        ld v1, 0x0A
+        xor v0, v1
+        ld i, pass
+        se v0, 0x06
+        ld i, fail
+        call mark
+
+; 8xy4 ADD result
+        ld v0, 0x12
+        ld v1, 0x34
+        add v0, v1
+        ld i, pass
+        se v0, 0x46
+        ld i, fail
+        call mark
+
+; 8xy5 SUB result
+        ld v0, 0x34
+        ld v1, 0x12
+        sub v0, v1
+        ld i, pass
+        se v0, 0x22
+        ld i, fail
+        call mark
+
+; 8xy7 SUBN result
+        ld v0, 0x12
+        ld v1, 0x34
+        subn v0, v1
+        ld i, pass
+        se v0, 0x22
+        ld i, fail
+        call mark
+
+; 2nnn and 00EE return after the call
+        ld v0, 0
+        call sub_inc
+        ld i, pass
+        se v0, 1
+        ld i, fail
+        call mark
+
+; Bnnn jumps to nnn + V0, V2 holds the same offset for the Bxnn variant
+        ld v0, 4
+        ld v2, 4
+        ld v3, 4
+        ld v1, 0
+        jpx jump_table
+after_jump: ld i, pass
+        se v1, 2
+        ld i, fail
+        call mark
+
+; Fx15 and Fx07 set and read the delay timer
+        ld v0, 0x80
+        ld dt, v0
+        ld v1, dt
+        ld i, pass
+        se v1, 0x80
+        ld i, fail
+        call mark
+
+; Fx29 points I to the font sprite of the digit
+        ld v0, 3
+        ld f, v0
+        ld v0, [i]
+        ld i, pass
+        se v0, 0xF0
+        ld i, fail
+        call mark
+
+; Fx33 stores the decimal digits of Vx
+        ld v0, 254
+        ld i, scratch
+        ld b, v0
+        ld i, scratch
+        ld v2, [i]
+        ld i, fail
+        se v0, 2
+        jp t17
+        se v1, 5
+        jp t17
+        se v2, 4
+        jp t17
+        ld i, pass
+t17:    call mark
+
+; Fx55 and Fx65 store and load V0 to Vx
+        ld v0, 0x11
+        ld v1, 0x22
+        ld v2, 0x33
+        ld i, scratch
+        ld [i], v2
+        ld v0, 0
+        ld v1, 0
+        ld v2, 0
+        ld i, scratch
+        ld v2, [i]
+        ld i, fail
+        se v0, 0x11
+        jp t18
+        se v1, 0x22
+        jp t18
+        se v2, 0x33
+        jp t18
+        ld i, pass
+t18:    call mark
+
+; Fx1E adds Vx to I
+        ld v0, 3
+        ld i, digits
+        add i, v0
+        ld v0, [i]
+        ld i, pass
+        se v0, 0xDD
+        ld i, fail
+        call mark
+
+; Cxkk with a zero mask always gives 0
+        ld v0, 0xFF
+        rnd v0, 0
+        ld i, pass
+        se v0, 0
+        ld i, fail
+        call mark
+
+done:   jp done
+
+sub_inc: add v0, 1
+        ret
+
+jump_table: ld v1, 1
+        jp after_jump
+        ld v1, 2
+        jp after_jump
+
+digits: db 0xAA 0xBB 0xCC 0xDD
+scratch: db 0 0 0 0
+; Draws the sprite at I as the next result mark, 8 marks per row
+mark:   drw v6, v7, 4
+        add v6, 8
+        sne v6, 64
+        jp mark_nl
+        ret
+mark_nl: ld v6, 0
+        add v7, 6
+        ret
+
+; Filled square for a passed check, cross for a failed one
+pass:   db 0xF0 0xF0 0xF0 0xF0
+fail:   db 0x90 0x60 0x60 0x90
@@ -0,0 +1,81 @@
+; Shows which quirks are active, one mark per quirk: a filled square when the quirk
+; behaviour is observed, a cross otherwise
+        ld v6, 0
+        ld v7, 0
+
+; VF reset: 8xy1 sets VF to 0
+        ld v0, 0x01
+        ld v1, 0x02
+        ld vf, 0x05
+        or v0, v1
+        ld i, pass
+        se vf, 0
+        ld i, fail
+        call mark
+
+; Shift: 8xy6 shifts Vy into Vx
+        ld v0, 0x10
+        ld v1, 0x04
+        shr v0, v1
+        ld i, pass
+        se v0, 0x02
+        ld i, fail
+        call mark
+
+; Load/store: Fx55 leaves I after the last register stored
+        ld v0, 0xAA
+        ld i, scratch
+        ld [i], v0
+        ld v0, [i]
+        ld i, pass
+        se v0, 0x00
+        ld i, fail
+        call mark
+
+; Jump: Bxnn jumps to xnn + Vx
+        ld v0, 0
+        ld v1, 0
+        ld v2, 4
+        ld v3, 4
+        jpx jump_table
+after_jump: ld i, pass
+        se v1, 2
+        ld i, fail
+        call mark
+
+; Clipping: a sprite crossing the right edge does not wrap around to the left
+        ld v0, 60
+        ld v1, 31
+        ld i, row
+        drw v0, v1, 1
+        ld v0, 0
+        ld i, dot
+        drw v0, v1, 1
+        ld i, pass
+        se vf, 0
+        ld i, fail
+        call mark
+
+done:   jp done
+
+jump_table: ld v1, 1
+        jp after_jump
+        ld v1, 2
+        jp after_jump
+
+row:    db 0xFF
+dot:    db 0x80
+scratch: db 0 0
+; Draws the sprite at I as the next result mark, 8 marks per row
+mark:   drw v6, v7, 4
+        add v6, 8
+        sne v6, 64
+        jp mark_nl
+        ret
+mark_nl: ld v6, 0
+        add v7, 6
+        ret
+
+; Filled square for a passed check, cross for a failed one
+pass:   db 0xF0 0xF0 0xF0 0xF0
+fail:   db 0x90 0x60 0x60 0x90
@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chip8oxyde::chip8::audio::{Buzzer, Waveform};
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::config;
+use chip8oxyde::frontend::headless::{Headless, KeyScript};
+use chip8oxyde::frontend::trace::{ReferenceTrace, TraceComparison};
+
+const FRAMES: u64 = 30;
+
+fn headless(rom: &str) -> Headless {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/roms")
+        .join(format!("{}.ch8", rom));
+
+    let mut chip8 = Chip8::new();
+    chip8.load(&fs::read(path).unwrap()).unwrap();
+
+    let buzzer = Buzzer::new(
+        Waveform::Square { duty: 0.5 },
+        config::CHIP8_SOUND_NOTE_FREQ,
+        0.0,
+        config::CHIP8_SOUND_SAMPLE_RATE as u32,
+    );
+    Headless::new(chip8, buzzer, KeyScript::default())
+}
+
+fn trace(rom: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    headless(rom)
+        .run_traced(FRAMES, &mut |chip8| {
+            lines.push(chip8.trace_line());
+            Ok(())
+        })
+        .unwrap();
+
+    lines
+}
+
+fn compare(rom: &str, frames: u64, reference: &str) -> Result<usize, String> {
+    let mut comparison = TraceComparison::new(ReferenceTrace::parse(reference).unwrap());
+    headless(rom)
+        .run_traced(frames, &mut |chip8| {
+            comparison.push(chip8);
+            Ok(())
+        })
+        .unwrap();
+
+    comparison
+        .finish()
+        .map_err(|divergence| divergence.to_string())
+}
+
+#[test]
+fn matching_trace() {
+    let lines = trace("opcodes");
+    assert_eq!(
+        compare("opcodes", FRAMES, &lines.join("\n")),
+        Ok(lines.len())
+    );
+
+    // A shorter reference, with a comment, lowercase hex and some fields left out
+    let reference: Vec<_> = lines[..20]
+        .iter()
+        .map(|line| {
+            line.split(" I=")
+                .next()
+                .unwrap()
+                .to_lowercase()
+                .replace("pc=", "PC=")
+        })
+        .collect();
+    let reference = format!("# From another emulator\n{}", reference.join("\n"));
+    assert_eq!(compare("opcodes", FRAMES, &reference), Ok(20));
+}
+
+#[test]
+fn first_divergence() {
+    let mut lines = trace("opcodes");
+    lines[40] = lines[40].replace(" VF=", " VF=7F VX=");
+    lines[60] = lines[60].replace("PC=0", "PC=F");
+
+    let report = compare("opcodes", FRAMES, &lines.join("\n")).unwrap_err();
+    assert!(report.starts_with("Divergence at instruction 41 (reference line 41): VF="));
+    assert!(report.contains(&format!("  {}\n", lines[39])));
+    assert!(report.contains(&format!("- {}\n", lines[40])));
+}
+
+#[test]
+fn run_shorter_than_reference() {
+    let lines = trace("opcodes");
+
+    let report = compare("opcodes", 1, &lines.join("\n")).unwrap_err();
+    assert!(report.starts_with("The program stopped after "));
+}
+
+#[test]
+fn invalid_reference() {
+    let error = ReferenceTrace::parse("PC=0200 OP=00E0\nPC=0202 OP=zz").err();
+    assert_eq!(error, Some("Line 2: 'zz' is not a hex value".to_string()));
+}
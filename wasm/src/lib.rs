@@ -0,0 +1,144 @@
+// The interpreter for wasm32-unknown-unknown, driven from JavaScript. Data is exchanged through
+// buffers in the module's memory: the host copies a program into `rom_buffer` before calling
+// `load_rom`, and reads the RGBA pixels at `framebuffer` after each frame
+
+use std::sync::Mutex;
+
+use chip8oxyde::chip8::quirks::QuirkProfile;
+use chip8oxyde::chip8::Chip8;
+use chip8oxyde::config;
+
+const ROM_SIZE: usize = config::CHIP8_MEMORY_SIZE - config::CHIP8_PROGRAM_LOAD_ADDRESS;
+const FRAMEBUFFER_SIZE: usize = config::CHIP8_WIDTH as usize * config::CHIP8_HEIGHT as usize * 4;
+
+struct Machine {
+    chip8: Chip8,
+    sound_playing: bool,
+    // Set when the program failed, frames are then no longer run
+    stopped: bool,
+}
+
+static ROM: Mutex<[u8; ROM_SIZE]> = Mutex::new([0; ROM_SIZE]);
+static FRAMEBUFFER: Mutex<[u8; FRAMEBUFFER_SIZE]> = Mutex::new([0; FRAMEBUFFER_SIZE]);
+static MACHINE: Mutex<Option<Machine>> = Mutex::new(None);
+static QUIRKS: Mutex<QuirkProfile> = Mutex::new(QuirkProfile::Modern);
+
+fn update_framebuffer(chip8: &Chip8) {
+    let mut framebuffer = FRAMEBUFFER.lock().unwrap();
+    let colors = chip8.screen().pixel_colors();
+
+    for (rgba, rgb) in framebuffer.chunks_exact_mut(4).zip(colors.chunks_exact(3)) {
+        rgba[..3].copy_from_slice(rgb);
+        rgba[3] = 0xFF;
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn rom_buffer() -> *mut u8 {
+    ROM.lock().unwrap().as_mut_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn rom_buffer_size() -> usize {
+    ROM_SIZE
+}
+
+// Picks the quirks of the next loaded program, by index in modern, cosmac and schip
+#[no_mangle]
+pub extern "C" fn set_quirks(profile: u32) -> bool {
+    match QuirkProfile::ALL.get(profile as usize) {
+        Some(profile) => {
+            *QUIRKS.lock().unwrap() = *profile;
+            true
+        }
+        None => false,
+    }
+}
+
+// Starts the first `size` bytes of the ROM buffer, returns false if they don't fit in memory
+#[no_mangle]
+pub extern "C" fn load_rom(size: usize, seed: u32) -> bool {
+    let rom = ROM.lock().unwrap();
+    if size > rom.len() {
+        return false;
+    }
+
+    let mut chip8 = Chip8::new();
+    chip8.seed(seed as u64);
+    chip8.set_quirks(QUIRKS.lock().unwrap().quirks());
+    if chip8.load(&rom[..size]).is_err() {
+        return false;
+    }
+    update_framebuffer(&chip8);
+
+    *MACHINE.lock().unwrap() = Some(Machine {
+        chip8,
+        sound_playing: false,
+        stopped: false,
+    });
+
+    true
+}
+
+// Runs one 60 Hz frame, returns false once the program has stopped on an error
+#[no_mangle]
+pub extern "C" fn step_frame() -> bool {
+    let mut machine = MACHINE.lock().unwrap();
+    let machine = match machine.as_mut() {
+        Some(machine) if !machine.stopped => machine,
+        _ => return false,
+    };
+
+    match machine.chip8.run_frame() {
+        Ok(sound_playing) => machine.sound_playing = sound_playing,
+        Err(_) => {
+            machine.sound_playing = false;
+            machine.stopped = true;
+        }
+    }
+    update_framebuffer(&machine.chip8);
+
+    !machine.stopped
+}
+
+// Pixels of the display as RGBA bytes, row by row
+#[no_mangle]
+pub extern "C" fn framebuffer() -> *const u8 {
+    FRAMEBUFFER.lock().unwrap().as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn framebuffer_width() -> u32 {
+    config::CHIP8_WIDTH
+}
+
+#[no_mangle]
+pub extern "C" fn framebuffer_height() -> u32 {
+    config::CHIP8_HEIGHT
+}
+
+#[no_mangle]
+pub extern "C" fn set_key(key: u32, down: bool) {
+    let key = key as usize;
+    if key >= config::CHIP8_KEY_COUNT {
+        return;
+    }
+
+    if let Some(machine) = MACHINE.lock().unwrap().as_mut() {
+        if down {
+            machine.chip8.key_down(key);
+        } else {
+            machine.chip8.key_up(key);
+        }
+    }
+}
+
+// Whether the buzzer sounded during the last frame
+#[no_mangle]
+pub extern "C" fn audio_flag() -> bool {
+    MACHINE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|machine| machine.sound_playing)
+}
@@ -0,0 +1,55 @@
+// Runs the test ROMs in the built module without a browser and compares the final display with
+// the golden files of the native tests: node test.mjs
+
+import { readFileSync, readdirSync } from 'fs';
+import { Chip8 } from './www/chip8oxyde.js';
+
+const WASM = new URL('target/wasm32-unknown-unknown/release/chip8oxyde_wasm.wasm', import.meta.url);
+const TESTS = new URL('../tests/', import.meta.url);
+// The same as tests/golden.rs
+const FRAMES = 120;
+
+const module = await WebAssembly.compile(readFileSync(WASM));
+let failures = 0;
+
+for (const file of readdirSync(new URL('golden/', TESTS))) {
+  const [, rom, quirks] = file.match(/^(.+)-(\w+)\.txt$/);
+  const chip8 = new Chip8((await WebAssembly.instantiate(module, {})).exports);
+  chip8.load(readFileSync(new URL(`roms/${rom}.ch8`, TESTS)), { quirks, seed: 0 });
+
+  let keys = [];
+  try {
+    keys = readFileSync(new URL(`roms/${rom}.keys`, TESTS), 'utf8')
+      .split('\n')
+      .map((line) => line.split('#')[0].trim().split(/\s+/))
+      .filter((fields) => fields.length === 3);
+  } catch {}
+
+  for (let frame = 0; frame < FRAMES; frame++) {
+    for (const [at, key, action] of keys) {
+      if (Number(at) === frame) {
+        chip8.setKey(parseInt(key, 16), action === 'down');
+      }
+    }
+    if (!chip8.stepFrame()) {
+      break;
+    }
+  }
+
+  // Lit pixels are not black in the default palette
+  const pixels = chip8.framebuffer();
+  let display = '';
+  for (let y = 0; y < chip8.height; y++) {
+    for (let x = 0; x < chip8.width; x++) {
+      display += pixels[(y * chip8.width + x) * 4] !== 0 ? '#' : '.';
+    }
+    display += '\n';
+  }
+
+  const golden = readFileSync(new URL(`golden/${file}`, TESTS), 'utf8');
+  const result = display === golden ? 'ok' : 'FAILED';
+  failures += result === 'ok' ? 0 : 1;
+  console.log(`${rom} ${quirks}: ${result}`);
+}
+
+process.exit(failures === 0 ? 0 : 1);
@@ -0,0 +1,162 @@
+// Browser host of the interpreter compiled to WebAssembly
+
+const QUIRK_PROFILES = ['modern', 'cosmac', 'schip'];
+const KEYS = '0123456789abcdef';
+const FRAME_TIME = 1000 / 60;
+// Frames run at most per animation frame, when the page was in the background
+const MAX_FRAME_LAG = 4;
+
+export async function compileModule(url = new URL('chip8oxyde_wasm.wasm', import.meta.url)) {
+  const response = await fetch(url);
+  return WebAssembly.compile(await response.arrayBuffer());
+}
+
+// Each instance of the module holds a single machine
+export async function createChip8(module) {
+  const instance = await WebAssembly.instantiate(module, {});
+  return new Chip8(instance.exports);
+}
+
+// The machine, over the exports of the module
+export class Chip8 {
+  constructor(exports) {
+    this.exports = exports;
+    this.width = exports.framebuffer_width();
+    this.height = exports.framebuffer_height();
+  }
+
+  load(rom, { quirks = 'modern', seed = Date.now() >>> 0 } = {}) {
+    const { exports } = this;
+
+    if (!exports.set_quirks(QUIRK_PROFILES.indexOf(quirks))) {
+      throw new Error(`Unknown quirk profile ${quirks}`);
+    }
+    if (rom.length > exports.rom_buffer_size()) {
+      throw new Error('The program size is too large');
+    }
+
+    new Uint8Array(exports.memory.buffer, exports.rom_buffer(), rom.length).set(rom);
+    if (!exports.load_rom(rom.length, seed)) {
+      throw new Error('The program size is too large');
+    }
+  }
+
+  // Returns false once the program stopped on an error
+  stepFrame() {
+    return this.exports.step_frame() !== 0;
+  }
+
+  // RGBA pixels, a new view every time as the memory of the module can grow
+  framebuffer() {
+    const { exports } = this;
+    return new Uint8ClampedArray(exports.memory.buffer, exports.framebuffer(), this.width * this.height * 4);
+  }
+
+  setKey(key, down) {
+    this.exports.set_key(key, down);
+  }
+
+  soundPlaying() {
+    return this.exports.audio_flag() !== 0;
+  }
+}
+
+// Square wave buzzer, the audio context can only start after a user gesture
+class Buzzer {
+  constructor() {
+    this.context = null;
+    this.gain = null;
+  }
+
+  resume() {
+    if (!this.context) {
+      this.context = new AudioContext();
+      const oscillator = this.context.createOscillator();
+      oscillator.type = 'square';
+      oscillator.frequency.value = 440;
+      this.gain = this.context.createGain();
+      this.gain.gain.value = 0;
+      oscillator.connect(this.gain).connect(this.context.destination);
+      oscillator.start();
+    }
+    this.context.resume();
+  }
+
+  set(playing) {
+    if (this.gain) {
+      this.gain.gain.setTargetAtTime(playing ? 0.05 : 0, this.context.currentTime, 0.002);
+    }
+  }
+}
+
+// Runs a program in a canvas, which takes the keyboard when focused: the hex digits are the keys.
+// Returns a function that stops it
+export function play(canvas, chip8) {
+  const context = canvas.getContext('2d');
+  const image = new ImageData(chip8.width, chip8.height);
+  const buzzer = new Buzzer();
+  canvas.width = chip8.width;
+  canvas.height = chip8.height;
+  canvas.tabIndex = 0;
+  canvas.style.imageRendering = 'pixelated';
+
+  const onKey = (event) => {
+    const key = KEYS.indexOf(event.key.toLowerCase());
+    if (event.key.length !== 1 || key < 0) {
+      return;
+    }
+    event.preventDefault();
+    buzzer.resume();
+    chip8.setKey(key, event.type === 'keydown');
+  };
+  canvas.addEventListener('keydown', onKey);
+  canvas.addEventListener('keyup', onKey);
+  canvas.addEventListener('pointerdown', () => buzzer.resume());
+
+  let running = true;
+  let last = performance.now();
+  let lag = 0;
+  const frame = (now) => {
+    if (!running) {
+      return;
+    }
+
+    lag = Math.min(lag + now - last, FRAME_TIME * MAX_FRAME_LAG);
+    last = now;
+    while (lag >= FRAME_TIME) {
+      lag -= FRAME_TIME;
+      running = chip8.stepFrame();
+    }
+
+    image.data.set(chip8.framebuffer());
+    context.putImageData(image, 0, 0);
+    buzzer.set(running && chip8.soundPlaying());
+
+    if (running) {
+      requestAnimationFrame(frame);
+    }
+  };
+  requestAnimationFrame(frame);
+
+  return () => {
+    running = false;
+    buzzer.set(false);
+  };
+}
+
+// Plays the program at `data-rom` in every canvas with that attribute, for embedding in pages
+export async function mountAll(root = document) {
+  const canvases = root.querySelectorAll('canvas[data-rom]');
+  if (canvases.length === 0) {
+    return;
+  }
+
+  const module = await compileModule();
+  for (const canvas of canvases) {
+    const response = await fetch(canvas.dataset.rom);
+    const rom = new Uint8Array(await response.arrayBuffer());
+    const chip8 = await createChip8(module);
+    chip8.load(rom, { quirks: canvas.dataset.quirks || 'modern' });
+    play(canvas, chip8);
+  }
+}